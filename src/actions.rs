@@ -0,0 +1,522 @@
+use std::{
+    fs::OpenOptions,
+    io::{BufRead, BufReader, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    sync::mpsc::{self, RecvTimeoutError},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::{
+    fields::render_template,
+    trigger::{TriggerEvent, TriggerOutcome, TriggerStatus},
+};
+
+/// Default seconds before an incomplete batch is sent.
+pub const ACTION_FLUSH: u64 = 1;
+/// Default attempts after a failed delivery.
+pub const ACTION_RETRIES: u32 = 3;
+/// Delay before the first retry, doubled on each attempt.
+const RETRY_DELAY: Duration = Duration::from_millis(200);
+/// Timeout of each step of an HTTP request.
+const HTTP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Built-in trigger action, as written in the configuration file.
+///
+/// Exactly one of `file`, `post` or `fifo` must be set.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ActionRule {
+    /// Appends the lines to this file
+    pub file: Option<PathBuf>,
+    /// POSTs the lines as JSON to this `http://` URL
+    pub post: Option<String>,
+    /// Writes the lines to this named pipe
+    pub fifo: Option<PathBuf>,
+    /// How lines are written to a file or FIFO, e.g. `{container}: {captures.level} {line}`
+    pub template: Option<String>,
+    /// Lines delivered at once [default: 1]
+    pub batch: Option<usize>,
+    /// Seconds before an incomplete batch is delivered [default: 1]
+    pub flush: Option<u64>,
+    /// Attempts after a failed delivery [default: 3]
+    pub retries: Option<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    File(PathBuf),
+    Post(HttpUrl),
+    Fifo(PathBuf),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct HttpUrl {
+    host: String,
+    port: u16,
+    path: String,
+}
+
+impl ActionRule {
+    fn target(&self) -> Result<Target, String> {
+        match (&self.file, &self.post, &self.fifo) {
+            (Some(file), None, None) => Ok(Target::File(file.clone())),
+            (None, Some(url), None) => Ok(Target::Post(parse_url(url)?)),
+            (None, None, Some(fifo)) => Ok(Target::Fifo(fifo.clone())),
+            _ => Err("an action needs one of `file`, `post` or `fifo`".to_string()),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.batch == Some(0) {
+            return Err("`batch` must be at least 1".to_string());
+        }
+        self.target().map(|_| ())
+    }
+}
+
+/// Parses `http://host[:port][/path]`.
+fn parse_url(url: &str) -> Result<HttpUrl, String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| format!("`{url}` isn't an http:// URL"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    let (host, port) = match authority.rsplit_once(':') {
+        Some((host, port)) => (
+            host,
+            port.parse()
+                .map_err(|_| format!("`{port}` isn't a valid port"))?,
+        ),
+        None => (authority, 80),
+    };
+    if host.is_empty() {
+        return Err(format!("`{url}` has no host"));
+    }
+
+    Ok(HttpUrl {
+        host: host.to_string(),
+        port,
+        path: path.to_string(),
+    })
+}
+
+/// Fields of an event, as seen by templates and in the JSON that is posted.
+fn event_fields(event: &TriggerEvent) -> Value {
+    let captures: serde_json::Map<String, Value> = event
+        .captures
+        .iter()
+        .map(|(name, value)| (name.clone(), Value::String(value.clone())))
+        .collect();
+    json!({
+        "line": event.line,
+        "container": event.container,
+        "captures": captures,
+    })
+}
+
+/// Built-in action of a container, delivering the events on its own thread.
+#[derive(Debug)]
+pub struct Action {
+    sender: mpsc::Sender<TriggerEvent>,
+}
+
+impl Action {
+    /// Starts the action of the container `id`, its outcomes are sent to `results`.
+    pub fn new(
+        rule: &ActionRule,
+        id: u8,
+        results: Option<mpsc::Sender<TriggerOutcome>>,
+    ) -> Result<Self, String> {
+        rule.validate()?;
+        let worker = Worker {
+            target: rule.target()?,
+            template: rule
+                .template
+                .clone()
+                .unwrap_or_else(|| "{line}".to_string()),
+            batch: rule.batch.unwrap_or(1),
+            flush: Duration::from_secs(rule.flush.unwrap_or(ACTION_FLUSH)),
+            retries: rule.retries.unwrap_or(ACTION_RETRIES),
+            id,
+            results,
+        };
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || worker.run(&receiver));
+        Ok(Self { sender })
+    }
+
+    pub fn send(&self, event: TriggerEvent) {
+        self.sender.send(event).ok();
+    }
+}
+
+struct Worker {
+    target: Target,
+    template: String,
+    batch: usize,
+    flush: Duration,
+    retries: u32,
+    id: u8,
+    results: Option<mpsc::Sender<TriggerOutcome>>,
+}
+
+impl Worker {
+    /// Collects events in batches until the action is dropped.
+    fn run(&self, receiver: &mpsc::Receiver<TriggerEvent>) {
+        let mut batch = vec![];
+        let mut deadline: Option<Instant> = None;
+        loop {
+            let received = match deadline {
+                Some(deadline) => {
+                    receiver.recv_timeout(deadline.saturating_duration_since(Instant::now()))
+                }
+                None => receiver.recv().map_err(|_| RecvTimeoutError::Disconnected),
+            };
+            let disconnected = match received {
+                Ok(event) => {
+                    if batch.is_empty() {
+                        deadline = Some(Instant::now() + self.flush);
+                    }
+                    batch.push(event);
+                    if batch.len() < self.batch {
+                        continue;
+                    }
+                    false
+                }
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => true,
+            };
+            if !batch.is_empty() {
+                self.deliver_with_retries(&batch);
+                batch.clear();
+                deadline = None;
+            }
+            if disconnected {
+                break;
+            }
+        }
+    }
+
+    fn deliver_with_retries(&self, batch: &[TriggerEvent]) {
+        let mut delay = RETRY_DELAY;
+        let mut result = self.deliver(batch);
+        for _ in 0..self.retries {
+            if result.is_ok() {
+                break;
+            }
+            thread::sleep(delay);
+            delay *= 2;
+            result = self.deliver(batch);
+        }
+        let (status, message) = match result {
+            Ok(message) => (TriggerStatus::Done, message),
+            Err(e) => (TriggerStatus::Failed(e), String::new()),
+        };
+        let mut outcome = TriggerOutcome::new(self.id, batch[0].clone(), status);
+        outcome.stdout = message;
+        if let Some(results) = &self.results {
+            results.send(outcome).ok();
+        }
+    }
+
+    /// Returns what was done, or why it could not be done.
+    fn deliver(&self, batch: &[TriggerEvent]) -> Result<String, String> {
+        match &self.target {
+            Target::File(path) => {
+                let file = OpenOptions::new().create(true).append(true).open(path);
+                self.write_lines(file, batch, path)
+            }
+            Target::Fifo(path) => self.write_lines(open_fifo(path), batch, path),
+            Target::Post(url) => {
+                let body = match batch {
+                    [event] => event_fields(event),
+                    _ => Value::Array(batch.iter().map(event_fields).collect()),
+                };
+                post(url, &body.to_string())
+            }
+        }
+    }
+
+    fn write_lines(
+        &self,
+        file: std::io::Result<std::fs::File>,
+        batch: &[TriggerEvent],
+        path: &Path,
+    ) -> Result<String, String> {
+        let mut text = String::new();
+        for event in batch {
+            text.push_str(&render_template(&self.template, &event_fields(event)));
+            text.push('\n');
+        }
+        file.and_then(|mut file| file.write_all(text.as_bytes()))
+            .map_err(|e| format!("{}: {e}", path.display()))?;
+        Ok(format!("wrote {} lines to {}", batch.len(), path.display()))
+    }
+}
+
+/// Opens a named pipe without waiting for a reader, failing if there is none.
+///
+/// The writes block, so that a full pipe never takes only part of a batch.
+fn open_fifo(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut options = OpenOptions::new();
+    options.write(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.custom_flags(libc::O_NONBLOCK);
+    }
+    let file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::io::AsRawFd;
+        let fd = file.as_raw_fd();
+        // SAFETY: `fd` is open for as long as `file` lives
+        let flags = unsafe { libc::fcntl(fd, libc::F_GETFL) };
+        if flags < 0 || unsafe { libc::fcntl(fd, libc::F_SETFL, flags & !libc::O_NONBLOCK) } < 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+    Ok(file)
+}
+
+/// POSTs the JSON body and returns the status line of a successful response.
+fn post(url: &HttpUrl, body: &str) -> Result<String, String> {
+    let address = (url.host.as_str(), url.port)
+        .to_socket_addrs()
+        .map_err(|e| e.to_string())?
+        .next()
+        .ok_or_else(|| format!("{} not found", url.host))?;
+    let mut stream =
+        TcpStream::connect_timeout(&address, HTTP_TIMEOUT).map_err(|e| e.to_string())?;
+    stream
+        .set_read_timeout(Some(HTTP_TIMEOUT))
+        .and_then(|_| stream.set_write_timeout(Some(HTTP_TIMEOUT)))
+        .map_err(|e| e.to_string())?;
+    let request = format!(
+        "POST {} HTTP/1.1\r\nHost: {}:{}\r\nUser-Agent: logss\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        url.path,
+        url.host,
+        url.port,
+        body.len()
+    );
+    stream
+        .write_all(request.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let mut status_line = String::new();
+    BufReader::new(stream)
+        .read_line(&mut status_line)
+        .map_err(|e| e.to_string())?;
+    let status_line = status_line.trim_end().to_string();
+    match status_line.split_whitespace().nth(1) {
+        Some(code) if code.starts_with('2') => Ok(status_line),
+        _ => Err(format!("unexpected response `{status_line}`")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{io::Read, net::TcpListener};
+
+    use super::*;
+
+    fn event(line: &str) -> TriggerEvent {
+        let mut event = TriggerEvent::new(line, "(?P<level>ERROR)");
+        event
+            .captures
+            .push(("level".to_string(), "ERROR".to_string()));
+        event
+    }
+
+    #[test]
+    fn rules() {
+        let rule = ActionRule {
+            post: Some("http://localhost:8080/hook".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            rule.target(),
+            Ok(Target::Post(HttpUrl {
+                host: "localhost".to_string(),
+                port: 8080,
+                path: "/hook".to_string()
+            }))
+        );
+        assert_eq!(parse_url("http://example.com").unwrap().port, 80);
+        assert_eq!(parse_url("http://example.com").unwrap().path, "/");
+        assert!(parse_url("https://example.com").is_err());
+        assert!(parse_url("http://:80/").is_err());
+        assert!(parse_url("http://host:port/").is_err());
+        assert!(ActionRule::default().validate().is_err());
+        let both = ActionRule {
+            file: Some("a".into()),
+            fifo: Some("b".into()),
+            ..Default::default()
+        };
+        assert!(both.validate().is_err());
+        let empty_batch = ActionRule {
+            file: Some("a".into()),
+            batch: Some(0),
+            ..Default::default()
+        };
+        assert!(empty_batch.validate().is_err());
+    }
+
+    #[test]
+    fn append_to_file() {
+        let path = std::env::temp_dir().join(format!("logss-action-{}", std::process::id()));
+        let rule = ActionRule {
+            file: Some(path.clone()),
+            template: Some("{captures.level} {line}".to_string()),
+            batch: Some(2),
+            ..Default::default()
+        };
+        let (sender, receiver) = mpsc::channel();
+        let action = Action::new(&rule, 3, Some(sender)).unwrap();
+        action.send(event("ERROR one"));
+        action.send(event("ERROR two"));
+        action.send(event("ERROR three"));
+        let outcome = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(outcome.id, 3);
+        assert_eq!(outcome.status, TriggerStatus::Done);
+        assert!(outcome.stdout.starts_with("wrote 2 lines to"));
+        // The incomplete batch is flushed
+        let outcome = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(outcome.event.line, "ERROR three");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "ERROR ERROR one\nERROR ERROR two\nERROR ERROR three\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn post_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = thread::spawn(move || {
+            let mut requests = vec![];
+            for response in ["HTTP/1.1 500 Oops", "HTTP/1.1 200 OK"] {
+                let (mut stream, _) = listener.accept().unwrap();
+                let mut request = vec![0; 4096];
+                let len = stream.read(&mut request).unwrap();
+                requests.push(String::from_utf8_lossy(&request[..len]).to_string());
+                write!(stream, "{response}\r\nContent-Length: 0\r\n\r\n").unwrap();
+            }
+            requests
+        });
+
+        let rule = ActionRule {
+            post: Some(format!("http://127.0.0.1:{port}/hook")),
+            retries: Some(1),
+            ..Default::default()
+        };
+        let (sender, receiver) = mpsc::channel();
+        let action = Action::new(&rule, 1, Some(sender)).unwrap();
+        action.send(event("ERROR \"quoted\""));
+        let outcome = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(outcome.status, TriggerStatus::Done);
+        assert_eq!(outcome.stdout, "HTTP/1.1 200 OK");
+
+        let requests = server.join().unwrap();
+        // Retried after the error
+        assert_eq!(requests[0], requests[1]);
+        assert!(requests[1].starts_with("POST /hook HTTP/1.1\r\n"));
+        assert!(requests[1].contains("Content-Type: application/json\r\n"));
+        assert!(requests[1].ends_with(
+            r#"{"line":"ERROR \"quoted\"","container":"(?P<level>ERROR)","captures":{"level":"ERROR"}}"#
+        ));
+    }
+
+    #[test]
+    fn failed_delivery() {
+        // Nothing listens on it once the listener is dropped
+        let port = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port();
+        let rule = ActionRule {
+            post: Some(format!("http://127.0.0.1:{port}/")),
+            retries: Some(0),
+            ..Default::default()
+        };
+        let (sender, receiver) = mpsc::channel();
+        let action = Action::new(&rule, 1, Some(sender)).unwrap();
+        action.send(event("ERROR"));
+        let outcome = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(matches!(outcome.status, TriggerStatus::Failed(_)));
+        assert!(outcome.failed());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn write_to_fifo() {
+        let path = std::env::temp_dir().join(format!("logss-fifo-{}", std::process::id()));
+        let status = std::process::Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let rule = ActionRule {
+            fifo: Some(path.clone()),
+            ..Default::default()
+        };
+        // Without a reader
+        assert!(open_fifo(&path).is_err());
+
+        let (sender, receiver) = mpsc::channel();
+        let action = Action::new(&rule, 1, Some(sender)).unwrap();
+        let reader_path = path.clone();
+        let reader = thread::spawn(move || std::fs::read_to_string(reader_path).unwrap());
+        action.send(event("ERROR fifo"));
+        let outcome = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(outcome.status, TriggerStatus::Done);
+        assert_eq!(reader.join().unwrap(), "ERROR fifo\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fifo_full() {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        let path = std::env::temp_dir().join(format!("logss-fifo-full-{}", std::process::id()));
+        let status = std::process::Command::new("mkfifo")
+            .arg(&path)
+            .status()
+            .unwrap();
+        assert!(status.success());
+        let mut reader = OpenOptions::new()
+            .read(true)
+            .custom_flags(libc::O_NONBLOCK)
+            .open(&path)
+            .unwrap();
+        let mut writer = open_fifo(&path).unwrap();
+        // Far more than the pipe holds, written as a whole once the reader catches up
+        let text = "x".repeat(1 << 18);
+        let expected = text.len();
+        let writing = thread::spawn(move || writer.write_all(text.as_bytes()));
+        let mut read = 0;
+        let mut buf = vec![0; 1 << 16];
+        while read < expected {
+            match reader.read(&mut buf) {
+                // The writer gave up
+                Ok(0) => break,
+                Ok(len) => read += len,
+                Err(e) if e.kind() == std::io::ErrorKind::WouldBlock => {
+                    thread::sleep(Duration::from_millis(10));
+                }
+                Err(e) => panic!("{e}"),
+            }
+        }
+        assert!(writing.join().unwrap().is_ok());
+        assert_eq!(read, expected);
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,245 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// Default window of `above` rules, in seconds.
+pub const ALERT_WINDOW: u64 = 60;
+
+/// When an alert fires, as written in the configuration file.
+///
+/// Either `above` (with `within`) or `absent` must be set.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct AlertRule {
+    /// Fires when there are more than this many matches...
+    pub above: Option<u64>,
+    /// ...within this many seconds [default: 60]
+    pub within: Option<u64>,
+    /// Fires when there is no match for this many seconds
+    pub absent: Option<u64>,
+    /// Seconds before the alert can fire again [default: 0]
+    pub cooldown: Option<u64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Condition {
+    Above { count: u64, within: Duration },
+    Absent(Duration),
+}
+
+/// Rate based alert of a container.
+///
+/// It fires once when its condition starts to hold and not again until the
+/// condition clears. If it starts to hold again within the cooldown, it fires once
+/// the cooldown has passed, as long as it still holds.
+#[derive(Debug)]
+pub struct Alert {
+    condition: Condition,
+    cooldown: Duration,
+    /// newest matches, only as many as the condition needs
+    matches: VecDeque<Instant>,
+    /// time of the last match, or of the creation of the alert
+    last_match: Instant,
+    firing_since: Option<Instant>,
+    last_fired: Option<Instant>,
+    /// firing but held back by the cooldown
+    pending: bool,
+}
+
+impl Alert {
+    pub fn new(rule: &AlertRule, now: Instant) -> Result<Self, String> {
+        let condition = match (rule.above, rule.absent) {
+            (Some(count), None) => Condition::Above {
+                count,
+                within: Duration::from_secs(rule.within.unwrap_or(ALERT_WINDOW)),
+            },
+            (None, Some(absent)) if rule.within.is_none() => {
+                Condition::Absent(Duration::from_secs(absent))
+            }
+            (None, Some(_)) => return Err("`within` only applies to `above`".to_string()),
+            _ => return Err("an alert needs either `above` or `absent`".to_string()),
+        };
+
+        Ok(Self {
+            condition,
+            cooldown: Duration::from_secs(rule.cooldown.unwrap_or(0)),
+            matches: VecDeque::new(),
+            last_match: now,
+            firing_since: None,
+            last_fired: None,
+            pending: false,
+        })
+    }
+
+    /// Records a match at `now`.
+    pub fn record(&mut self, now: Instant) {
+        self.last_match = now;
+        if let Condition::Above { count, .. } = self.condition {
+            self.matches.push_back(now);
+            while self.matches.len() as u64 > count + 1 {
+                self.matches.pop_front();
+            }
+        }
+    }
+
+    fn holds(&self, now: Instant) -> bool {
+        match self.condition {
+            Condition::Above { count, within } => {
+                self.matches.len() as u64 > count
+                    && self
+                        .matches
+                        .front()
+                        .is_some_and(|first| now.saturating_duration_since(*first) <= within)
+            }
+            Condition::Absent(absent) => now.saturating_duration_since(self.last_match) >= absent,
+        }
+    }
+
+    /// Updates the state of the alert, returns true if the trigger has to run.
+    pub fn check(&mut self, now: Instant) -> bool {
+        match (self.holds(now), self.firing_since) {
+            (true, None) => {
+                self.firing_since = Some(now);
+                self.pending = true;
+            }
+            (false, Some(_)) => {
+                self.firing_since = None;
+                self.pending = false;
+            }
+            _ => (),
+        }
+        let cooled_down = self.last_fired.map_or(true, |fired| {
+            now.saturating_duration_since(fired) >= self.cooldown
+        });
+        if !(self.pending && cooled_down) {
+            return false;
+        }
+        self.pending = false;
+        self.last_fired = Some(now);
+        true
+    }
+
+    /// When the alert started firing, `None` if it is not firing
+    pub fn firing_since(&self) -> Option<Instant> {
+        self.firing_since
+    }
+}
+
+impl fmt::Display for Alert {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.condition {
+            Condition::Above { count, within } => {
+                write!(f, "more than {count} in {}s", within.as_secs())
+            }
+            Condition::Absent(absent) => write!(f, "no match for {}s", absent.as_secs()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(above: Option<u64>, absent: Option<u64>, cooldown: Option<u64>) -> AlertRule {
+        AlertRule {
+            above,
+            absent,
+            cooldown,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn above() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut alert = Alert::new(&rule(Some(2), None, None), start).unwrap();
+        assert_eq!(alert.to_string(), "more than 2 in 60s");
+        alert.record(at(0));
+        alert.record(at(30));
+        assert!(!alert.check(at(30)));
+        // The first match is out of the window
+        alert.record(at(61));
+        assert!(!alert.check(at(61)));
+        alert.record(at(62));
+        assert!(alert.check(at(62)));
+        assert_eq!(alert.firing_since(), Some(at(62)));
+        // Fires only once
+        alert.record(at(63));
+        assert!(!alert.check(at(63)));
+        assert_eq!(alert.matches.len(), 3);
+        // Clears once the matches are old enough
+        assert!(!alert.check(at(200)));
+        assert_eq!(alert.firing_since(), None);
+    }
+
+    #[test]
+    fn absent() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut alert = Alert::new(&rule(None, Some(300), None), start).unwrap();
+        assert_eq!(alert.to_string(), "no match for 300s");
+        assert!(!alert.check(at(299)));
+        assert!(alert.check(at(300)));
+        assert!(!alert.check(at(400)));
+        alert.record(at(401));
+        assert!(!alert.check(at(401)));
+        assert_eq!(alert.firing_since(), None);
+        assert!(alert.check(at(701)));
+    }
+
+    #[test]
+    fn cooldown() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut alert = Alert::new(&rule(None, Some(10), Some(60)), start).unwrap();
+        assert!(alert.check(at(10)));
+        alert.record(at(11));
+        assert!(!alert.check(at(11)));
+        // Firing again, but too soon to run the trigger
+        assert!(!alert.check(at(21)));
+        assert!(alert.firing_since().is_some());
+        alert.record(at(80));
+        assert!(!alert.check(at(80)));
+        assert!(alert.check(at(90)));
+    }
+
+    #[test]
+    fn cooldown_pending() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_secs(secs);
+        let mut alert = Alert::new(&rule(None, Some(10), Some(60)), start).unwrap();
+        assert!(alert.check(at(10)));
+        alert.record(at(11));
+        assert!(!alert.check(at(11)));
+        // Holds again within the cooldown, and still does once it has passed
+        assert!(!alert.check(at(21)));
+        assert!(!alert.check(at(69)));
+        assert!(alert.check(at(70)));
+        assert!(!alert.check(at(71)));
+        // Cleared before the cooldown passed, nothing left to fire
+        alert.record(at(72));
+        assert!(!alert.check(at(72)));
+        assert!(!alert.check(at(82)));
+        alert.record(at(83));
+        assert!(!alert.check(at(83)));
+        alert.record(at(130));
+        assert!(!alert.check(at(130)));
+    }
+
+    #[test]
+    fn invalid_rules() {
+        let now = Instant::now();
+        assert!(Alert::new(&rule(None, None, None), now).is_err());
+        assert!(Alert::new(&rule(Some(1), Some(1), None), now).is_err());
+        let within = AlertRule {
+            absent: Some(1),
+            within: Some(1),
+            ..Default::default()
+        };
+        assert!(Alert::new(&within, now).is_err());
+    }
+}
@@ -1,27 +1,49 @@
 use std::{
+    collections::HashMap,
     ops::{Deref, DerefMut},
+    str::FromStr,
     sync::mpsc::TryRecvError,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
 use crossterm::event::KeyCode;
 use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
-    text::Line,
+    style::{Color, Style},
+    text::{Line, Span},
     Frame,
 };
-use threadpool::ThreadPool;
+use regex::Regex;
 
 use crate::{
-    args::{parse_args, Args},
+    args::{parse_args, Args, LocalContainer},
     bars::render_bar_chart,
     container::{Container, CONTAINERS_MAX, CONTAINER_BUFFER, CONTAINER_COLORS},
+    context::Context,
+    export::{export, export_path, ExportFormat},
+    fields::LineParser,
     help::render_help,
     input::Input,
+    multiline::{Grouper, Multiline},
+    output::OUTPUT_TEMPLATE,
+    queue::{QueueStats, TRIGGER_QUEUE},
+    rates::render_rate_chart,
+    replay::Speed,
+    search::{line_text, Search},
+    session::{self, SESSION_FILE},
     states::{AppState, ScrollDirection, Views},
-    tstdin::StdinHandler,
+    status::render_status,
+    trigger::TriggerHistory,
+    tstdin::{InputLine, SourceEvent, StdinHandler, STDIN_SOURCE},
 };
 
+/// Maximum number of input lines consumed on each tick.
+///
+/// Whatever is left stays in the channel for the next tick so that a burst of
+/// input can not freeze the interface.
+pub const MAX_LINES_PER_TICK: usize = 10_000;
+
 /// Application.
 ///
 /// This is the main application.
@@ -33,8 +55,21 @@ pub struct App<'a> {
     stdin: StdinHandler,
     pub raw_buffer: Container<'a>,
     pub single_buffer: Container<'a>,
-    thread_pool: ThreadPool,
     args: Args,
+    /// names of the input sources
+    sources: Vec<String>,
+    /// parser of the lines of each source
+    parsers: HashMap<String, LineParser>,
+    /// outcomes of the triggers
+    pub triggers: TriggerHistory,
+    /// search in the raw, single or zoomed view
+    pub search: Search,
+    /// lines matching any of these are hidden everywhere
+    pub exclude: Vec<Regex>,
+    /// assembles multi-line events
+    grouper: Grouper,
+    /// how the buffers are exported
+    pub export_format: ExportFormat,
 }
 
 impl Deref for App<'_> {
@@ -61,12 +96,18 @@ impl Default for App<'_> {
             single_buffer: Container::new_clean("single"),
             containers: Vec::new(),
             state: AppState::default(),
-            thread_pool: ThreadPool::new(4),
+            sources: Vec::new(),
+            parsers: HashMap::new(),
+            triggers: TriggerHistory::default(),
+            search: Search::default(),
+            exclude: Vec::new(),
+            grouper: Grouper::default(),
+            export_format: ExportFormat::default(),
         }
     }
 }
 
-impl App<'_> {
+impl<'a> App<'a> {
     /// Constructs a new instance of [`App`].
     pub fn new(args: Option<Args>) -> Self {
         let mut ret = Self::default();
@@ -81,7 +122,25 @@ impl App<'_> {
             }
 
             threads = ret.args.threads.unwrap_or(1);
-            ret.thread_pool = ThreadPool::new(threads as usize);
+        }
+        ret.exclude = ret
+            .args
+            .exclude
+            .iter()
+            .filter_map(|pattern| Regex::new(pattern).ok())
+            .collect();
+        if let Some(rule) = &ret.args.multiline {
+            if let Ok(multiline) = Multiline::new(rule) {
+                ret.grouper.set_rule(None, multiline);
+            }
+        }
+        for source in ret.args.input_sources() {
+            let Some(rule) = &source.multiline else {
+                continue;
+            };
+            if let Ok(multiline) = Multiline::new(rule) {
+                ret.grouper.set_rule(source.name.as_deref(), multiline);
+            }
         }
 
         // Let 0 for raw_buffer
@@ -93,32 +152,221 @@ impl App<'_> {
                 threads,
                 CONTAINER_BUFFER,
             );
-            if let Some(output_path) = ret.args.output.clone() {
-                con.set_output_path(output_path).ok();
+            if let Some(expr) = &c.expr {
+                con.set_expr(expr).ok();
+            }
+            con.context = Context::new(
+                c.before.or(c.context).unwrap_or(0),
+                c.after.or(c.context).unwrap_or(0),
+            );
+            if let Some(source) = &c.source {
+                con.set_source(source).ok();
+            }
+            for exclude in c.exclude.iter().flatten() {
+                con.add_exclude(exclude).ok();
+            }
+            if let Some(colors) = &c.colors {
+                con.set_colors(colors).ok();
+            }
+            if let Some(when) = &c.when {
+                con.set_when(when).ok();
             }
-            con.state.color = CONTAINER_COLORS[(id - 1) as usize];
+            con.columns = c.columns.clone().unwrap_or_default();
+            if let Some(value) = &c.value {
+                con.set_series(value);
+            }
+            con.trigger_input = c.trigger_input.unwrap_or_default();
+            if let Some(alerts) = &c.alerts {
+                con.set_alerts(alerts).ok();
+            }
+            con.state.color = c
+                .color
+                .as_deref()
+                .and_then(|color| Color::from_str(color).ok())
+                .unwrap_or(CONTAINER_COLORS[(id - 1) as usize]);
+            con.state.hide = c.hide.unwrap_or_default();
+            for line in c.buffer.iter().flatten() {
+                con.restore(line);
+            }
+            con.config = LocalContainer {
+                buffer: None,
+                ..c.clone()
+            };
             con.id = id;
+            if let Err(e) = set_output(&ret.args, &mut con) {
+                ret.state.set_status("output", e.to_string());
+            }
+            con.results = Some(ret.triggers.sender());
+            if let Some(actions) = &c.actions {
+                con.set_actions(actions).ok();
+            }
+            if let Some(queue) = &mut con.queue {
+                queue.capacity = c.queue.or(ret.args.queue).unwrap_or(TRIGGER_QUEUE);
+                queue.overflow = c.overflow.or(ret.args.overflow).unwrap_or_default();
+            }
             ret.containers.push(con);
         }
+        for line in ret.args.raw_buffer.take().into_iter().flatten() {
+            ret.raw_buffer.restore(&line);
+        }
+        ret.export_format = ret.args.export_format.unwrap_or_default();
+        ret.state.wrap = ret.args.wrap.unwrap_or_default();
+        ret.state.unfold = ret.args.unfold.unwrap_or_default();
+        if ret.args.raw.unwrap_or_default() {
+            ret.state.show = Views::RawBuffer;
+        }
+        if let Some(id) = ret.args.zoom {
+            ret.zoom_into(id);
+        }
         if ret.containers.is_empty() {
             ret.state.show = Views::RawBuffer;
         }
         ret
     }
 
+    /// Configuration that brings the session back: the containers as they are now
+    /// and the layout, with the lines of the buffers if `session_buffers` is set.
+    pub fn session(&self) -> Args {
+        let buffers = self.args.session_buffers.unwrap_or_default();
+        let mut containers: Vec<&Container> = self.containers.iter().collect();
+        containers.sort_by_key(|c| c.id);
+        let mut args = self.args.clone();
+        args.config_file = None;
+        // Resuming must not overwrite the recording
+        args.record = None;
+        args.exclude = self.exclude.iter().map(|re| re.to_string()).collect();
+        args.containers = containers.iter().map(|c| c.session(buffers)).collect();
+        // Ids are given in order when resuming
+        args.zoom = match self.state.show {
+            Views::Zoom => containers
+                .iter()
+                .position(|c| Some(c.id) == self.state.zoom_id)
+                .map(|i| i as u8 + 1),
+            _ => None,
+        };
+        args.raw = (self.state.show == Views::RawBuffer).then_some(true);
+        args.single = (self.state.show == Views::SingleBuffer).then_some(true);
+        args.vertical = (self.state.direction == Direction::Horizontal).then_some(true);
+        args.wrap = self.state.wrap.then_some(true);
+        args.unfold = self.state.unfold.then_some(true);
+        args.raw_buffer = buffers.then(|| {
+            self.raw_buffer
+                .cb
+                .ordered_clone()
+                .buffer
+                .iter()
+                .map(line_text)
+                .collect()
+        });
+        args
+    }
+
+    /// Exports the lines of the raw, single or zoomed view, or of every container shown,
+    /// scrolled off lines included.
+    pub fn export(&mut self) {
+        let dir = self.args.export_dir.clone().unwrap_or_else(|| ".".into());
+        let views: Vec<(String, &Container)> = match self.state.show {
+            Views::RawBuffer => vec![("raw".to_string(), &self.raw_buffer)],
+            Views::SingleBuffer => vec![("single".to_string(), &self.single_buffer)],
+            _ => self
+                .containers
+                .iter()
+                .filter(|c| match self.state.show {
+                    Views::Zoom => Some(c.id) == self.state.zoom_id,
+                    _ => !c.state.hide,
+                })
+                .map(|c| (format!("{}-{}", c.id, c.slug()), c))
+                .collect(),
+        };
+        let mut exported = vec![];
+        for (name, container) in views {
+            let path = export_path(&dir, &name, self.export_format);
+            let lines = container.cb.ordered_clone().buffer;
+            if let Err(e) = export(&lines, self.export_format, &path) {
+                let message = format!("failed to write {}: {e}", path.display());
+                self.set_status("export", message);
+                return;
+            }
+            exported.push((path, lines.len()));
+        }
+        let message = match exported.as_slice() {
+            [] => "nothing to export".to_string(),
+            [(path, lines)] => format!("{lines} lines to {}", path.display()),
+            paths => format!("{} files to {}", paths.len(), dir.display()),
+        };
+        self.set_status("export", message);
+    }
+
+    /// Switches to the next export format.
+    pub fn next_export_format(&mut self) {
+        self.export_format = self.export_format.next();
+        let message = format!("{} format", self.export_format);
+        self.set_status("export", message);
+    }
+
+    /// Saves the session so that it can be resumed with `-f`.
+    pub fn save_session(&mut self) {
+        let path = self
+            .args
+            .session
+            .clone()
+            .unwrap_or_else(|| SESSION_FILE.into());
+        let message = match session::save(&self.session(), &path) {
+            Ok(()) => format!("saved to {}", path.display()),
+            Err(e) => format!("failed to save: {e}"),
+        };
+        self.set_status("session", message);
+    }
+
     pub fn init(&mut self) -> Result<()> {
         self.state.running = true;
-        self.stdin.init(self.args.command.clone())?;
+        let sources = self.args.input_sources();
+        self.sources = sources.iter().filter_map(|s| s.name.clone()).collect();
+        let format = self.args.format.unwrap_or_default();
+        let template = self.args.template.clone();
+        self.parsers = sources
+            .iter()
+            .map(|s| {
+                let parser = LineParser::new(
+                    s.format.unwrap_or(format),
+                    s.template.clone().or(template.clone()),
+                );
+                (s.name.clone().unwrap_or_default(), parser)
+            })
+            .collect();
+        if sources.is_empty() {
+            let parser = LineParser::new(format, template);
+            self.parsers.insert(STDIN_SOURCE.to_string(), parser);
+        }
+        if let Some(path) = &self.args.record {
+            self.stdin.record(path)?;
+        }
+        match &self.args.replay {
+            Some(path) => {
+                let speed = self.args.speed.unwrap_or_default();
+                self.stdin.replay(path, speed)?;
+                if speed == Speed::Step {
+                    self.set_status("replay", "press > for the next line".to_string());
+                }
+            }
+            None => self.stdin.init(&sources)?,
+        }
         Ok(())
     }
 
+    /// Replays one more line of a stepped replay.
+    pub fn step_replay(&mut self) {
+        self.stdin.step();
+    }
+
     pub fn update_input(&mut self, key_code: KeyCode) {
         match key_code {
-            KeyCode::Enter => {
-                if self.add_input_as_container() {
-                    self.hide_show_input();
-                    self.state.show = Views::Containers;
-                }
+            KeyCode::Enter if self.input.is_exclude() && self.add_input_as_exclude() => {
+                self.hide_show_input();
+            }
+            KeyCode::Enter if self.add_input_as_container() => {
+                self.hide_show_input();
+                self.state.show = Views::Containers;
             }
             KeyCode::Char(c) => {
                 self.input.push(c);
@@ -133,10 +381,117 @@ impl App<'_> {
         }
     }
 
+    /// Opens the search prompt, dropping the previous search.
+    pub fn start_search(&mut self) {
+        self.search = Search::default();
+        self.state.searching = true;
+    }
+
+    /// Updates the search as it is typed, jumping to the newest hit.
+    pub fn update_search(&mut self, key_code: KeyCode) {
+        match key_code {
+            KeyCode::Enter => self.state.searching = false,
+            KeyCode::Esc => {
+                self.state.searching = false;
+                self.search = Search::default();
+                self.follow();
+            }
+            KeyCode::Char(c) => {
+                self.search.push(c);
+                self.next_hit(true);
+            }
+            KeyCode::Backspace => {
+                self.search.pop();
+                self.next_hit(true);
+            }
+            _ => {}
+        }
+    }
+
+    /// Jumps to the next hit below (`forward`) or above, stopping the auto-scroll of
+    /// the view while the input keeps being processed.
+    pub fn next_hit(&mut self, forward: bool) {
+        let Some((lines, first)) = self.focused().map(|c| (c.lines(), c.first_line())) else {
+            return;
+        };
+        if self.search.step(&lines, first, forward).is_some() {
+            if let Some(container) = self.focused_mut() {
+                container.state.scroll_lock = true;
+            }
+        }
+    }
+
+    /// Lets every view auto-scroll again after a search hit stopped it.
+    pub fn follow(&mut self) {
+        for container in self
+            .containers
+            .iter_mut()
+            .chain([&mut self.raw_buffer, &mut self.single_buffer])
+        {
+            container.state.scroll_lock = false;
+        }
+    }
+
+    /// Container shown alone: the raw, single or zoomed one
+    fn focused(&self) -> Option<&Container<'a>> {
+        match self.state.show {
+            Views::RawBuffer => Some(&self.raw_buffer),
+            Views::SingleBuffer => Some(&self.single_buffer),
+            Views::Zoom => self
+                .containers
+                .iter()
+                .find(|c| Some(c.id) == self.state.zoom_id),
+            _ => None,
+        }
+    }
+
+    fn focused_mut(&mut self) -> Option<&mut Container<'a>> {
+        match self.state.show {
+            Views::RawBuffer => Some(&mut self.raw_buffer),
+            Views::SingleBuffer => Some(&mut self.single_buffer),
+            Views::Zoom => self
+                .containers
+                .iter_mut()
+                .find(|c| Some(c.id) == self.state.zoom_id),
+            _ => None,
+        }
+    }
+
+    /// Adds the input as an exclude pattern of the zoomed container, or of every line
+    /// in the other views.
+    ///
+    /// An empty pattern removes the exclude patterns instead.
+    pub fn add_input_as_exclude(&mut self) -> bool {
+        let Some(pattern) = self.input.exclude_pattern() else {
+            return false;
+        };
+        let Ok(re) = Regex::new(&pattern) else {
+            return false;
+        };
+        let zoomed = match self.state.show {
+            Views::Zoom => self
+                .containers
+                .iter_mut()
+                .find(|c| Some(c.id) == self.state.zoom_id),
+            _ => None,
+        };
+        let exclude = match zoomed {
+            Some(container) => &mut container.exclude,
+            None => &mut self.exclude,
+        };
+        if pattern.is_empty() {
+            exclude.clear();
+        } else {
+            exclude.push(re);
+        }
+        self.input.reset();
+        true
+    }
+
     pub fn add_input_as_container(&mut self) -> bool {
         let is_valid = self.input.is_valid();
         if is_valid {
-            self.add_container(&self.input.inner_clone());
+            self.add_container(&self.input.pattern());
             self.input.reset();
         }
         is_valid
@@ -145,12 +500,17 @@ impl App<'_> {
     pub fn add_container(&mut self, text: &str) {
         let first_free_id = self.get_free_ids();
         let mut con = Container::new(text.to_string(), None, 1, 1, CONTAINER_BUFFER);
-        if let Some(output_path) = self.args.output.clone() {
-            con.set_output_path(output_path).ok();
-        }
+        con.config = LocalContainer {
+            re: text.to_string(),
+            ..Default::default()
+        };
         if let Some(inner_id) = first_free_id.first() {
             con.state.color = CONTAINER_COLORS[(inner_id - 1) as usize];
             con.id = *inner_id;
+            if let Err(e) = set_output(&self.args, &mut con) {
+                self.state.set_status("output", e.to_string());
+            }
+            con.results = Some(self.triggers.sender());
             self.containers.push(con);
         }
     }
@@ -211,36 +571,165 @@ impl App<'_> {
     /// Handles the tick event of the terminal.
     pub fn tick(&mut self) {
         self.get_stdin();
+        let now = Instant::now();
+        if !self.state.paused {
+            for event in self.grouper.flush_idle(now) {
+                self.handle_line(event);
+            }
+        }
+        for c in self.containers.iter_mut() {
+            c.check_alerts(now);
+            if let Some(e) = c.tick_output(now) {
+                self.state.set_status("output", e);
+            }
+        }
+        for outcome in self.triggers.collect() {
+            if !outcome.failed() {
+                continue;
+            }
+            if let Some(c) = self.containers.iter_mut().find(|c| c.id == outcome.id) {
+                c.state.trigger_failures += 1;
+            }
+        }
     }
 
-    fn handle_containers_with_line(&mut self, line: &str) {
+    fn handle_containers_with_line(&mut self, line: &InputLine) {
         for c in self.containers.iter_mut() {
-            if c.re.is_match(line) {
-                let ret = c.proc_and_push_line(line);
+            if !c.from_source(line) {
+                continue;
+            }
+            c.see(&self.raw_buffer.cb);
+            if !c.matches(line) {
+                c.push_after(&self.raw_buffer.cb);
+            } else {
+                if c.excludes(line) {
+                    c.state.excluded += 1;
+                    continue;
+                }
+                c.push_before(&self.raw_buffer.cb);
+                let ret = c.proc_and_push_input(line);
                 if let Some(l) = ret {
-                    self.single_buffer.cb.push(l.to_owned());
+                    self.single_buffer.push_line(l.to_owned());
                 }
             }
         }
     }
 
+    /// Drains the input channel, up to [`MAX_LINES_PER_TICK`] lines.
     fn get_stdin(&mut self) {
-        match self.stdin.try_recv() {
-            Ok(line) => {
-                // save all lines to a raw buffer
-                if !self.state.paused {
-                    self.raw_buffer.cb.push(Line::from(line.clone()));
-                    self.handle_containers_with_line(&line);
+        for received in 0..MAX_LINES_PER_TICK {
+            match self.stdin.try_recv() {
+                Ok(SourceEvent::Line(line)) => {
+                    if self.state.paused {
+                        continue;
+                    }
+                    if let Some(event) = self.grouper.push(line, Instant::now()) {
+                        self.handle_line(event);
+                    }
+                }
+                Ok(SourceEvent::Exited {
+                    source,
+                    status,
+                    restart_in,
+                }) => {
+                    for event in self.grouper.flush(&source) {
+                        self.handle_line(event);
+                    }
+                    let message = match status {
+                        Some(code) => format!("exited with code {code}"),
+                        None => "terminated by a signal".to_string(),
+                    };
+                    self.set_status(&source, restarting(message, restart_in));
+                }
+                Ok(SourceEvent::Failed {
+                    source,
+                    error,
+                    restart_in,
+                }) => {
+                    let message = format!("failed to start: {error}");
+                    self.set_status(&source, restarting(message, restart_in));
+                }
+                Ok(SourceEvent::Restarted { source, attempt }) => {
+                    self.set_status(&source, format!("restarted {attempt}x"));
+                    let separator = format!("{source} restarted");
+                    self.raw_buffer.push_separator(&separator);
+                    self.single_buffer.push_separator(&separator);
+                    for c in self.containers.iter_mut() {
+                        c.push_separator(&separator);
+                    }
+                }
+                Err(TryRecvError::Disconnected) => {
+                    for event in self.grouper.flush_all() {
+                        self.handle_line(event);
+                    }
+                    self.stop();
+                    break;
+                }
+                Err(TryRecvError::Empty) => {
+                    // Only exit once everything received was rendered
+                    if received == 0 && self.args.exit.unwrap_or_default() {
+                        for event in self.grouper.flush_all() {
+                            self.handle_line(event);
+                        }
+                        self.stop();
+                    }
+                    break;
                 }
             }
-            Err(TryRecvError::Disconnected) => self.stop(),
-            Err(TryRecvError::Empty) if self.args.exit.unwrap_or_default() => {
-                self.stop();
-            }
-            _ => {}
         }
     }
 
+    /// Saves a line, or a multi-line event, to the raw buffer and to the containers it matches.
+    fn handle_line(&mut self, mut line: InputLine) {
+        self.parse_line(&mut line);
+        if self.exclude.iter().any(|re| re.is_match(&line.text)) {
+            self.state.excluded += 1;
+            return;
+        }
+        let raw = self.raw_line(&line);
+        self.raw_buffer.push_line(raw);
+        self.handle_containers_with_line(&line);
+    }
+
+    /// Parses structured lines according to the format of their source.
+    fn parse_line(&self, line: &mut InputLine) {
+        if line.stderr {
+            return;
+        }
+        if let Some(parser) = self.parsers.get(&line.source) {
+            parser.parse(line);
+        }
+    }
+
+    /// Line for the raw buffer, prefixed with its source when there is more than one.
+    ///
+    /// Lines from the standard error of a command stand out.
+    fn raw_line<'b>(&self, line: &InputLine) -> Line<'b> {
+        let text = if line.stderr {
+            Span::styled(line.text.clone(), Style::default().fg(Color::LightRed))
+        } else {
+            Span::from(line.text.clone())
+        };
+        if self.sources.len() < 2 {
+            return Line::from(text);
+        }
+        let index = self
+            .sources
+            .iter()
+            .position(|s| *s == line.source)
+            .unwrap_or_default();
+        let color = CONTAINER_COLORS[index % CONTAINER_COLORS.len()];
+        Line::from(vec![
+            Span::styled(format!("[{}] ", line.source), Style::default().fg(color)),
+            text,
+        ])
+    }
+
+    /// Number of input lines waiting to be processed.
+    pub fn backlog(&self) -> usize {
+        self.stdin.pending()
+    }
+
     fn get_free_ids(&self) -> Vec<u8> {
         let used_ids: Vec<u8> = self.containers.iter().map(|c| c.id).collect();
         let mut free_ids: Vec<u8> = Vec::new();
@@ -283,14 +772,18 @@ impl App<'_> {
                 let container = &mut self.raw_buffer;
                 container.state.paused = self.state.paused;
                 container.state.wrap = self.state.wrap;
-                container.update_scroll(frame_rect.height as usize, &self.state.scroll_direction);
+                container.state.unfold = self.state.unfold;
+                let visible = container.visible_lines(frame_rect.height);
+                container.update_scroll(visible, &self.state.scroll_direction);
             }
             Views::SingleBuffer => {
                 // Single buffer
                 let container = &mut self.single_buffer;
                 container.state.paused = self.state.paused;
                 container.state.wrap = self.state.wrap;
-                container.update_scroll(frame_rect.height as usize, &self.state.scroll_direction);
+                container.state.unfold = self.state.unfold;
+                let visible = container.visible_lines(frame_rect.height);
+                container.update_scroll(visible, &self.state.scroll_direction);
             }
             _ => (),
         }
@@ -311,11 +804,42 @@ impl App<'_> {
             }
             container.state.paused = self.state.paused;
             container.state.wrap = self.state.wrap;
-            container.update_scroll(area as usize, &self.state.scroll_direction);
+            container.state.unfold = self.state.unfold;
+            let visible = container.visible_lines(area);
+            container.update_scroll(visible, &self.state.scroll_direction);
         }
 
         // Reset scroll direction so that scroll is done on each key press
         self.state.scroll_direction = ScrollDirection::NONE;
+        self.highlight_search(frame_rect);
+    }
+
+    /// Highlights the search in every container, the current hit only in the focused one.
+    fn highlight_search(&mut self, frame_rect: Rect) {
+        let search = self.search.is_active().then(|| {
+            let mut search = self.search.clone();
+            search.hit = None;
+            search
+        });
+        for container in self
+            .containers
+            .iter_mut()
+            .chain([&mut self.raw_buffer, &mut self.single_buffer])
+        {
+            container.search = search.clone();
+        }
+        if search.is_none() {
+            return;
+        }
+        let (search, jump) = (self.search.clone(), self.search.jump);
+        if let Some(container) = self.focused_mut() {
+            if let (true, Some(hit)) = (jump, search.hit) {
+                let index = hit.saturating_sub(container.first_line());
+                container.scroll_to(index, frame_rect.height as usize);
+            }
+            container.search = Some(search);
+        }
+        self.search.jump = false;
     }
 
     fn render_raw(&mut self, frame: &mut Frame) {
@@ -356,6 +880,64 @@ impl App<'_> {
         }
     }
 
+    fn render_rate_chart(&self, frame: &mut Frame) {
+        if self.state.rates && !self.containers.is_empty() {
+            render_rate_chart(frame, self);
+        }
+    }
+
+    fn render_triggers(&self, frame: &mut Frame) {
+        if self.state.triggers {
+            self.triggers.render(frame, &self.queue_stats());
+        }
+    }
+
+    /// Stats of the trigger queues of every container.
+    pub fn queue_stats(&self) -> QueueStats {
+        let mut stats = QueueStats::default();
+        for queue in self.containers.iter().filter_map(|c| c.queue.as_ref()) {
+            stats.add(&queue.stats());
+        }
+        stats
+    }
+
+    fn render_status(&self, frame: &mut Frame) {
+        let mut items = vec![];
+        let backlog = self.backlog();
+        if backlog > 0 {
+            items.push(format!("backlog: {backlog}"));
+        }
+        if !self.exclude.is_empty() {
+            items.push(format!("excluded: {}", self.state.excluded));
+        }
+        let queues = self.queue_stats();
+        if queues.queued > 0 || queues.dropped > 0 {
+            items.push(format!(
+                "triggers: {} queued, {} dropped",
+                queues.queued, queues.dropped
+            ));
+        }
+        for (topic, message) in self.state.status.iter() {
+            items.push(format!("{topic}: {message}"));
+        }
+        if self.search.is_active() && !self.state.searching {
+            let (current, total) = self.search_position();
+            items.push(format!("search: /{} {current}/{total}", self.search.query));
+        }
+        render_status(frame, &items);
+    }
+
+    fn search_position(&self) -> (usize, usize) {
+        self.focused()
+            .map_or((0, 0), |c| self.search.position(&c.lines(), c.first_line()))
+    }
+
+    fn render_search(&self, frame: &mut Frame) {
+        if self.state.searching {
+            self.search.render_prompt(frame, self.search_position());
+        }
+    }
+
     fn render_input(&self, frame: &mut Frame) {
         if self.state.show_input {
             self.input.render(frame);
@@ -388,13 +970,35 @@ impl App<'_> {
                 }
             }
         }
+        self.render_status(frame);
+        self.render_search(frame);
         // Popups need to go at the bottom
         self.render_help(frame);
         self.render_bar_chart(frame);
+        self.render_rate_chart(frame);
+        self.render_triggers(frame);
         self.render_input(frame);
     }
 }
 
+/// Status of a source that stopped, with the time left before it is restarted.
+fn restarting(mut message: String, restart_in: Option<Duration>) -> String {
+    if let Some(restart_in) = restart_in {
+        message.push_str(&format!(", restarting in {}s", restart_in.as_secs()));
+    }
+    message
+}
+
+/// Writes the matching lines of the container to a file when there is an output path.
+fn set_output(args: &Args, con: &mut Container) -> Result<()> {
+    if let Some(output_path) = args.output.clone() {
+        let template = args.output_template.as_deref().unwrap_or(OUTPUT_TEMPLATE);
+        let rotate = args.rotate.clone().unwrap_or_default();
+        con.set_output_path(output_path, template, &rotate)?;
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use ratatui::{
@@ -405,7 +1009,13 @@ mod tests {
     };
 
     use super::*;
-    use crate::args::LocalContainer;
+    use crate::{
+        args::LocalContainer,
+        multiline::{MultilineRule, MULTILINE_TIMEOUT},
+        queue::Overflow,
+        search::line_text,
+        trigger::TriggerStatus,
+    };
 
     #[test]
     fn test_new() {
@@ -436,11 +1046,13 @@ mod tests {
                 re: "a".to_string(),
                 trigger: None,
                 timeout: None,
+                ..Default::default()
             },
             LocalContainer {
                 re: "b".to_string(),
                 trigger: None,
                 timeout: None,
+                ..Default::default()
             },
         ];
         let app = App::new(Some(args));
@@ -513,6 +1125,203 @@ mod tests {
         assert_eq!(app.raw_buffer.cb.len(), 2);
     }
 
+    #[test]
+    fn get_stdin_drains_backlog() {
+        let mut app = App::new(None);
+        app.add_container("a");
+        app.init().unwrap();
+        let total = 100_000;
+        for _ in 0..total {
+            app.stdin.sender.send("abc".to_string()).unwrap();
+        }
+        assert_eq!(app.backlog(), total);
+
+        // Only a bounded amount of lines is consumed on each tick
+        app.tick();
+        assert_eq!(app.backlog(), total - MAX_LINES_PER_TICK);
+
+        while app.backlog() > 0 {
+            app.tick();
+        }
+        let c = app.containers.first().unwrap();
+        assert_eq!(c.get_count(), total as u64);
+        assert_eq!(c.cb.len(), CONTAINER_BUFFER);
+        assert_eq!(app.raw_buffer.cb.len(), CONTAINER_BUFFER);
+    }
+
+    #[test]
+    fn sources() {
+        let mut args = parse_args();
+        args.containers = vec![LocalContainer {
+            re: "a".to_string(),
+            source: Some("one".to_string()),
+            ..Default::default()
+        }];
+        let mut app = App::new(Some(args));
+        app.sources = vec!["one".to_string(), "two".to_string()];
+        app.state.running = true;
+        app.stdin
+            .sender
+            .send(InputLine::new("one", "abc".to_string()))
+            .unwrap();
+        app.stdin
+            .sender
+            .send(InputLine::new("two", "abc".to_string()))
+            .unwrap();
+        app.tick();
+
+        let c = app.containers.first().unwrap();
+        assert_eq!(c.get_count(), 1);
+        assert_eq!(app.raw_buffer.cb.len(), 2);
+        let expected = Line::from(vec![
+            Span::styled("[two] ", Style::default().fg(CONTAINER_COLORS[1])),
+            Span::from("abc"),
+        ]);
+        assert_eq!(app.raw_buffer.cb.buffer[1], expected);
+
+        // A single source has no prefix
+        app.sources.pop();
+        assert_eq!(
+            app.raw_line(&InputLine::new("one", "abc".to_string())),
+            Line::from("abc")
+        );
+        assert_eq!(
+            app.raw_line(&InputLine::new_stderr("one", "abc".to_string())),
+            Line::from(Span::styled("abc", Style::default().fg(Color::LightRed)))
+        );
+    }
+
+    #[test]
+    fn json_lines() {
+        let mut args = parse_args();
+        args.format = Some(crate::fields::Format::Json);
+        args.containers = vec![LocalContainer {
+            when: Some(r#"level == "error""#.to_string()),
+            ..Default::default()
+        }];
+        let mut app = App::new(Some(args));
+        app.init().unwrap();
+        app.stdin
+            .sender
+            .send(r#"{"level":"error","msg":"boom"}"#)
+            .unwrap();
+        app.stdin
+            .sender
+            .send(r#"{"level":"info","msg":"fine"}"#)
+            .unwrap();
+        app.stdin.sender.send("not json").unwrap();
+        app.tick();
+
+        let c = app.containers.first().unwrap();
+        assert_eq!(c.get_count(), 1);
+        // Without a regex there is nothing to highlight
+        assert_eq!(c.cb.buffer[0], Line::from("level=error msg=boom"));
+        assert_eq!(
+            app.raw_buffer.cb.buffer[1],
+            Line::from("level=info msg=fine")
+        );
+        assert_eq!(app.raw_buffer.cb.buffer[2], Line::from("not json"));
+    }
+
+    #[test]
+    fn source_exited() {
+        let mut app = App::new(None);
+        app.state.running = true;
+        app.stdin
+            .sender
+            .send(SourceEvent::Exited {
+                source: "kubectl".to_string(),
+                status: Some(1),
+                restart_in: None,
+            })
+            .unwrap();
+        app.tick();
+        assert!(app.is_running());
+        assert_eq!(app.state.status["kubectl"], "exited with code 1");
+
+        let backend = TestBackend::new(40, 3);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let last_line: String = (0..40).map(|x| buffer[(x, 2)].symbol()).collect();
+        assert!(last_line.ends_with(" kubectl: exited with code 1 "));
+    }
+
+    #[test]
+    fn source_restarted() {
+        let mut app = App::new(None);
+        app.add_container("a");
+        app.state.running = true;
+        app.stdin
+            .sender
+            .send(SourceEvent::Exited {
+                source: "kubectl".to_string(),
+                status: Some(1),
+                restart_in: Some(std::time::Duration::from_secs(2)),
+            })
+            .unwrap();
+        app.tick();
+        assert_eq!(
+            app.state.status["kubectl"],
+            "exited with code 1, restarting in 2s"
+        );
+
+        app.stdin
+            .sender
+            .send(SourceEvent::Restarted {
+                source: "kubectl".to_string(),
+                attempt: 1,
+            })
+            .unwrap();
+        app.tick();
+        assert_eq!(app.state.status["kubectl"], "restarted 1x");
+        let c = app.containers.first().unwrap();
+        assert_eq!(c.cb.len(), 1);
+        assert_eq!(c.get_count(), 0);
+        assert_eq!(app.raw_buffer.cb.len(), 1);
+        assert_eq!(app.single_buffer.cb.len(), 1);
+
+        app.stdin
+            .sender
+            .send(SourceEvent::Failed {
+                source: "kubectl".to_string(),
+                error: "No such file or directory (os error 2)".to_string(),
+                restart_in: Some(std::time::Duration::from_secs(4)),
+            })
+            .unwrap();
+        app.tick();
+        assert_eq!(
+            app.state.status["kubectl"],
+            "failed to start: No such file or directory (os error 2), restarting in 4s"
+        );
+    }
+
+    #[test]
+    fn sparkline_below_lines() {
+        let mut args = parse_args();
+        args.containers = vec![LocalContainer {
+            re: r"took (?P<took>\d+)".to_string(),
+            value: Some("took".to_string()),
+            ..Default::default()
+        }];
+        let mut app = App::new(Some(args));
+        app.state.running = true;
+        for i in 1..=30 {
+            app.stdin.sender.send(format!("took {i}")).unwrap();
+        }
+        app.tick();
+        let backend = TestBackend::new(40, 14);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let rows: Vec<String> = (0..14)
+            .map(|y| (0..40).map(|x| buffer[(x, y)].symbol()).collect())
+            .collect();
+        // The newest line is right above the sparkline
+        assert!(rows[9].contains("took 30"), "{rows:#?}");
+        assert!(rows[1].contains("took 22"), "{rows:#?}");
+    }
+
     #[test]
     fn get_layout_blocks() {
         let mut app = App::new(None);
@@ -570,6 +1379,530 @@ mod tests {
         terminal.backend().assert_buffer(&expected);
     }
 
+    #[test]
+    fn trigger_outcomes() {
+        let mut args = parse_args();
+        args.containers = vec![
+            LocalContainer {
+                re: "fail".to_string(),
+                trigger: Some("echo oops >&2; exit 1".to_string()),
+                ..Default::default()
+            },
+            LocalContainer {
+                re: "nul".to_string(),
+                trigger: Some("echo \0".to_string()),
+                ..Default::default()
+            },
+        ];
+        let mut app = App::new(Some(args));
+        app.init().unwrap();
+        app.stdin.sender.send("fail").unwrap();
+        app.stdin.sender.send("nul").unwrap();
+        app.tick();
+        for c in app.containers.iter() {
+            c.queue.as_ref().unwrap().join();
+        }
+        app.tick();
+
+        assert_eq!(app.containers[0].state.trigger_failures, 1);
+        assert_eq!(app.containers[1].state.trigger_failures, 1);
+        assert_eq!(app.triggers.outcomes.len(), 2);
+        let failed = app.triggers.outcomes.iter().find(|o| o.id == 1).unwrap();
+        assert_eq!(failed.status, TriggerStatus::Exited(Some(1)));
+        assert_eq!(failed.stderr, "oops");
+        let not_started = app.triggers.outcomes.iter().find(|o| o.id == 2).unwrap();
+        assert!(matches!(not_started.status, TriggerStatus::Failed(_)));
+
+        app.flip_triggers();
+        let backend = TestBackend::new(60, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|c| c.symbol()).collect();
+        assert!(text.contains("Triggers"));
+        assert!(text.contains("[1] exit 1 fail"));
+        assert!(text.contains("err: oops"));
+        assert!(text.contains("[1 failed triggers]"));
+    }
+
+    #[test]
+    fn trigger_queue() {
+        let mut args = parse_args();
+        args.queue = Some(5);
+        args.containers = vec![LocalContainer {
+            re: "slow".to_string(),
+            trigger: Some("sleep 1".to_string()),
+            queue: Some(1),
+            timeout: Some(5),
+            ..Default::default()
+        }];
+        let mut app = App::new(Some(args));
+        app.init().unwrap();
+        let queue = app.containers[0].queue.as_ref().unwrap();
+        assert_eq!(queue.capacity, 1);
+        assert_eq!(queue.overflow, Overflow::Drop);
+        for _ in 0..4 {
+            app.stdin.sender.send("slow").unwrap();
+        }
+        let started = Instant::now();
+        app.tick();
+        // The triggers do not run on the caller
+        assert!(started.elapsed() < std::time::Duration::from_millis(500));
+        let stats = app.queue_stats();
+        assert_eq!(stats.queued + stats.running + stats.dropped as usize, 4);
+        assert!(stats.dropped >= 2);
+
+        app.flip_triggers();
+        let backend = TestBackend::new(80, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|c| c.symbol()).collect();
+        assert!(text.contains("Queue: "));
+        assert!(text.contains("dropped"));
+        assert!(text.contains("triggers: "));
+    }
+
+    #[test]
+    fn exclude() {
+        let mut args = parse_args();
+        args.exclude = vec!["health".to_string()];
+        args.containers = vec![LocalContainer {
+            re: "GET".to_string(),
+            exclude: Some(vec!["/metrics".to_string()]),
+            ..Default::default()
+        }];
+        let mut app = App::new(Some(args));
+        app.state.running = true;
+        for line in ["GET /health", "GET /metrics", "GET /api", "GET /api/health"] {
+            app.stdin.sender.send(line).unwrap();
+        }
+        app.tick();
+        assert_eq!(app.raw_buffer.cb.len(), 2);
+        assert_eq!(app.state.excluded, 2);
+        assert_eq!(app.containers[0].get_count(), 1);
+        assert_eq!(app.containers[0].state.excluded, 1);
+
+        // Added to the zoomed container only
+        app.zoom_into(1);
+        app.flip_show_input();
+        for c in "!api".chars() {
+            app.update_input(KeyCode::Char(c));
+        }
+        app.update_input(KeyCode::Enter);
+        assert!(!app.show_input());
+        assert_eq!(app.state.show, Views::Zoom);
+        assert_eq!(app.containers[0].exclude.len(), 2);
+        assert_eq!(app.exclude.len(), 1);
+        let backend = TestBackend::new(60, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|c| c.symbol()).collect();
+        assert!(text.contains("[1]'GET' !'/metrics' !'api' (1) [1 excluded]"));
+        assert!(text.contains("excluded: 2"));
+
+        // Invalid patterns are kept in the input
+        app.zoom_into(1);
+        app.flip_show_input();
+        app.update_input(KeyCode::Char('!'));
+        app.update_input(KeyCode::Char('('));
+        app.update_input(KeyCode::Enter);
+        assert!(app.show_input());
+        // An empty pattern clears the global ones
+        app.update_input(KeyCode::Backspace);
+        app.update_input(KeyCode::Enter);
+        assert!(app.exclude.is_empty());
+        assert_eq!(app.containers.len(), 1);
+    }
+
+    #[test]
+    fn context() {
+        let mut args = parse_args();
+        args.containers = vec![LocalContainer {
+            re: "ERROR".to_string(),
+            before: Some(1),
+            context: Some(2),
+            ..Default::default()
+        }];
+        let mut app = App::new(Some(args));
+        app.state.running = true;
+        for line in [
+            "INFO start",
+            "INFO request",
+            "ERROR failed",
+            "  at a.rs:1",
+            "  at b.rs:2",
+            "INFO done",
+            "INFO idle",
+            "INFO request",
+            "ERROR failed again",
+        ] {
+            app.stdin.sender.send(line).unwrap();
+        }
+        app.tick();
+        let c = &app.containers[0];
+        let lines: Vec<String> = c.lines().iter().map(line_text).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "INFO request",
+                "ERROR failed",
+                "  at a.rs:1",
+                "  at b.rs:2",
+                "--",
+                "INFO request",
+                "ERROR failed again",
+            ]
+        );
+        assert_eq!(c.get_count(), 2);
+        assert_eq!(c.lines()[0].style.fg, Some(Color::DarkGray));
+    }
+
+    #[test]
+    fn context_sources() {
+        let mut args = parse_args();
+        args.containers = vec![LocalContainer {
+            re: "ERROR".to_string(),
+            source: Some("api".to_string()),
+            context: Some(1),
+            ..Default::default()
+        }];
+        let mut app = App::new(Some(args));
+        app.state.running = true;
+        for (source, text) in [
+            ("api", "GET /a"),
+            ("db", "query"),
+            ("api", "ERROR boom"),
+            ("db", "slow query"),
+            ("api", "GET /b"),
+            ("db", "idle"),
+        ] {
+            app.stdin
+                .sender
+                .send(InputLine::new(source, text.to_string()))
+                .unwrap();
+        }
+        app.tick();
+        let lines: Vec<String> = app.containers[0].lines().iter().map(line_text).collect();
+        assert_eq!(lines, vec!["GET /a", "ERROR boom", "GET /b"]);
+    }
+
+    #[test]
+    fn multiline() {
+        let mut args = parse_args();
+        args.multiline = Some(MultilineRule {
+            continuation: Some(r"^\s".to_string()),
+            ..Default::default()
+        });
+        args.containers = vec![LocalContainer {
+            re: "Exception".to_string(),
+            ..Default::default()
+        }];
+        let mut app = App::new(Some(args));
+        app.state.running = true;
+        for line in [
+            "ERROR request failed\n",
+            "java.lang.IllegalStateException: closed\n",
+            "    at a.Foo.bar(Foo.java:1)\n",
+            "    at a.Foo.main(Foo.java:2)\n",
+            "INFO retrying\n",
+        ] {
+            app.stdin.sender.send(line).unwrap();
+        }
+        app.tick();
+        // The last event is still open
+        assert_eq!(app.raw_buffer.cb.len(), 2);
+        let c = &app.containers[0];
+        assert_eq!(c.get_count(), 1);
+        assert_eq!(
+            c.lines()[0].to_string(),
+            "java.lang.IllegalStateException: closed [+2 lines]"
+        );
+
+        app.flip_unfold();
+        let backend = TestBackend::new(60, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let c = &app.containers[0];
+        let lines: Vec<String> = c.lines().iter().map(line_text).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "java.lang.IllegalStateException: closed",
+                "    at a.Foo.bar(Foo.java:1)",
+                "    at a.Foo.main(Foo.java:2)",
+            ]
+        );
+        let text: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|c| c.symbol())
+            .collect();
+        assert!(text.contains("at a.Foo.main"));
+
+        std::thread::sleep(MULTILINE_TIMEOUT);
+        app.tick();
+        assert_eq!(app.raw_buffer.cb.len(), 3);
+    }
+
+    #[test]
+    fn session() {
+        let mut args = parse_args();
+        args.containers = vec![LocalContainer {
+            re: "GET".to_string(),
+            trigger: Some("echo".to_string()),
+            ..Default::default()
+        }];
+        args.session_buffers = Some(true);
+        let mut app = App::new(Some(args));
+        app.state.running = true;
+        app.add_container("POST");
+        app.add_container("PUT");
+        app.remove_id(2);
+        app.add_container("DELETE");
+        for line in ["GET /a", "PUT /b", "DELETE /c"] {
+            app.stdin.sender.send(line).unwrap();
+        }
+        app.tick();
+        app.hide_view(1);
+        app.zoom_into(3);
+        app.containers[0].add_exclude("health").unwrap();
+        app.flip_wrap();
+
+        let path = std::env::temp_dir().join(format!("logss-app-session-{}", std::process::id()));
+        app.args.session = Some(path.clone());
+        app.save_session();
+        assert_eq!(
+            app.state.status["session"],
+            format!("saved to {}", path.display())
+        );
+        let args: Args = serde_yaml::from_reader(std::fs::File::open(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let resumed = App::new(Some(args));
+        let describe = |c: &Container| (c.id, c.text.clone(), c.state.color, c.state.hide);
+        assert_eq!(
+            resumed.containers.iter().map(describe).collect::<Vec<_>>(),
+            vec![
+                (1, "GET".to_string(), Color::Red, true),
+                (2, "DELETE".to_string(), Color::Blue, false),
+                (3, "PUT".to_string(), Color::Cyan, false),
+            ]
+        );
+        assert_eq!(resumed.containers[0].trigger, Some("echo".to_string()));
+        assert_eq!(resumed.containers[0].exclude.len(), 1);
+        let lines: Vec<String> = resumed.containers[2]
+            .lines()
+            .iter()
+            .map(line_text)
+            .collect();
+        assert_eq!(lines, vec!["PUT /b"]);
+        assert_eq!(resumed.containers[2].get_count(), 0);
+        assert_eq!(resumed.raw_buffer.cb.len(), 3);
+        assert_eq!(resumed.state.show, Views::Zoom);
+        assert_eq!(resumed.state.zoom_id, Some(3));
+        assert!(resumed.state.wrap);
+    }
+
+    #[test]
+    fn session_keeps_events() {
+        let mut args = parse_args();
+        args.multiline = Some(MultilineRule {
+            continuation: Some(r"^\s".to_string()),
+            ..Default::default()
+        });
+        args.session_buffers = Some(true);
+        let mut app = App::new(Some(args));
+        app.state.running = true;
+        for line in ["ERROR boom\n", "  at a\n", "INFO ok\n", "INFO open\n"] {
+            app.stdin.sender.send(line).unwrap();
+        }
+        app.tick();
+        assert_eq!(app.raw_buffer.cb.len(), 2);
+
+        let args = app.session();
+        assert_eq!(args.raw_buffer.as_ref().map(Vec::len), Some(2));
+        let mut resumed = App::new(Some(args));
+        assert_eq!(resumed.raw_buffer.cb.len(), 2);
+        resumed.raw_buffer.state.unfold = true;
+        let lines: Vec<String> = resumed.raw_buffer.lines().iter().map(line_text).collect();
+        assert_eq!(lines, vec!["ERROR boom", "  at a", "INFO ok"]);
+    }
+
+    #[test]
+    fn export() {
+        let dir = std::env::temp_dir().join(format!("logss-app-export-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut args = parse_args();
+        args.export_dir = Some(dir.clone());
+        args.containers = vec![LocalContainer {
+            re: "ERROR".to_string(),
+            ..Default::default()
+        }];
+        let mut app = App::new(Some(args));
+        app.state.running = true;
+        for line in ["ERROR a", "INFO b", "ERROR c"] {
+            app.stdin.sender.send(line).unwrap();
+        }
+        app.tick();
+        app.zoom_into(1);
+        app.export();
+        let message = &app.state.status["export"];
+        let path = message.strip_prefix("2 lines to ").unwrap();
+        assert!(path.contains("logss-1-error-"), "{path}");
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "ERROR a\nERROR c\n");
+
+        app.flip_raw_view();
+        app.next_export_format();
+        assert_eq!(app.state.status["export"], "jsonl format");
+        app.export();
+        let path = app.state.status["export"]
+            .strip_prefix("3 lines to ")
+            .unwrap()
+            .to_string();
+        assert!(path.ends_with(".jsonl"));
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .starts_with("{\"line\":\"ERROR a\"}\n"));
+
+        app.args.export_dir = Some(dir.join("missing"));
+        app.export();
+        assert!(app.state.status["export"].starts_with("failed to write"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn search_zoom() {
+        let mut app = App::new(None);
+        app.add_container("line");
+        for i in 0..100 {
+            app.containers[0].proc_and_push_line(&format!("line {i}"));
+        }
+        app.zoom_into(1);
+        app.start_search();
+        for c in "line 1".chars() {
+            app.update_search(KeyCode::Char(c));
+        }
+        // The newest hit, "line 19"
+        assert_eq!(app.search.hit, Some(19));
+        assert!(app.containers[0].state.scroll_lock);
+        assert!(!app.state.paused);
+        let backend = TestBackend::new(30, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        assert_eq!(app.containers[0].state.scroll, 14);
+        let buffer = terminal.backend().buffer();
+        let row = |y: u16| -> String { (0..30).map(|x| buffer[(x, y)].symbol()).collect() };
+        assert!(row(6).contains("line 19"));
+        assert_eq!(buffer[(1, 6)].bg, Color::LightMagenta);
+        assert_eq!(buffer[(1, 5)].bg, Color::Yellow);
+        // Prompt with the position of the hit
+        assert!(row(9).starts_with("/line 1 [11/11]"));
+
+        app.update_search(KeyCode::Enter);
+        app.next_hit(false);
+        terminal.draw(|f| app.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|c| c.symbol()).collect();
+        assert!(text.contains("search: /line 1 10/11"));
+        assert_eq!(app.containers[0].state.scroll, 13);
+    }
+
+    #[test]
+    fn search_keeps_reading() {
+        let mut app = App::new(None);
+        app.add_container("line");
+        app.state.running = true;
+        for i in 0..20 {
+            app.stdin.sender.send(format!("line {i}")).unwrap();
+        }
+        app.tick();
+        app.zoom_into(1);
+        app.start_search();
+        app.update_search(KeyCode::Char('5'));
+        app.update_search(KeyCode::Enter);
+        let backend = TestBackend::new(30, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let scroll = app.containers[0].state.scroll;
+
+        // Lines are still taken in, the view stays at the hit
+        app.stdin.sender.send("line 20").unwrap();
+        app.tick();
+        terminal.draw(|f| app.render(f)).unwrap();
+        assert_eq!(app.containers[0].get_count(), 21);
+        assert_eq!(app.containers[0].state.scroll, scroll);
+
+        app.follow();
+        terminal.draw(|f| app.render(f)).unwrap();
+        assert!(app.containers[0].state.scroll > scroll);
+    }
+
+    #[test]
+    fn search_full_buffer() {
+        let mut app = App::new(None);
+        app.add_container("line");
+        let push = |app: &mut App, i: usize| {
+            let mark = if i % 800 == 500 { " mark" } else { "" };
+            app.containers[0].proc_and_push_line(&format!("line {i}{mark}"));
+        };
+        for i in 0..CONTAINER_BUFFER {
+            push(&mut app, i);
+        }
+        app.zoom_into(1);
+        app.start_search();
+        for c in "mark".chars() {
+            app.update_search(KeyCode::Char(c));
+        }
+        app.update_search(KeyCode::Enter);
+        let backend = TestBackend::new(30, 10);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let scroll = app.containers[0].state.scroll;
+        let row = |terminal: &Terminal<TestBackend>, y: u16| -> String {
+            let buffer = terminal.backend().buffer();
+            (0..30).map(|x| buffer[(x, y)].symbol()).collect()
+        };
+        assert!(row(&terminal, 6).contains("line 500 mark"));
+
+        // The oldest lines are pushed out, the view and the hit stay on the same line
+        for i in CONTAINER_BUFFER..CONTAINER_BUFFER + 477 {
+            push(&mut app, i);
+        }
+        terminal.draw(|f| app.render(f)).unwrap();
+        assert_eq!(app.containers[0].first_line(), 477);
+        assert_eq!(app.search.hit, Some(500));
+        assert_eq!(app.containers[0].state.scroll, scroll - 477);
+        assert!(row(&terminal, 6).contains("line 500 mark"));
+        assert_eq!(terminal.backend().buffer()[(10, 6)].bg, Color::LightMagenta);
+
+        app.next_hit(true);
+        assert_eq!(app.search.hit, Some(1300));
+        terminal.draw(|f| app.render(f)).unwrap();
+        assert!(row(&terminal, 6).contains("line 1300 mark"));
+        assert_eq!(terminal.backend().buffer()[(11, 6)].bg, Color::LightMagenta);
+    }
+
+    #[test]
+    fn render_rate_chart() {
+        let mut app = App::new(None);
+        app.add_container("a");
+        app.containers[0].proc_and_push_line("a");
+        app.containers[0].proc_and_push_line("a");
+        app.flip_rates();
+        let backend = TestBackend::new(60, 20);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal.draw(|f| app.render(f)).unwrap();
+        let buffer = terminal.backend().buffer();
+        let text: String = buffer.content().iter().map(|c| c.symbol()).collect();
+        assert!(text.contains("Rates (lines/s, 1s buckets)"));
+        assert!(text.contains("-59s"));
+        assert!(text.contains("2.0"));
+    }
+
     #[test]
     fn render_id() {
         let mut app = App::new(None);
@@ -1,13 +1,31 @@
 use std::{
+    collections::BTreeMap,
     fs::{remove_file, OpenOptions},
+    path::Path,
     str::FromStr,
+    time::Instant,
 };
 
 use pico_args;
+use ratatui::style::Color;
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_yaml;
 
+use crate::{
+    actions::ActionRule,
+    alerts::{Alert, AlertRule},
+    export::ExportFormat,
+    expr::Expr,
+    fields::{Format, Predicate},
+    multiline::{Multiline, MultilineRule},
+    output::{validate_template, RotateRule},
+    queue::Overflow,
+    replay::Speed,
+    trigger::TriggerInput,
+    tstdin::{expand_follow, follow_name},
+};
+
 const HELP: &str = "\
 Simple CLI command to display logs in a user-friendly way
 
@@ -15,22 +33,125 @@ Usage: logss [OPTIONS]
 
 Options:
   -c <CONTAINERS>  Specify substrings (regex patterns)
+  -E <EXPR>        Container matching an expression, e.g. 'ERROR and not timeout'
+  -x <EXCLUDE>     Hide lines matching this pattern everywhere (repeatable)
   -e               Exit on empty input [default: false]
   -s               Start in single view mode [default: false]
-  -C <COMMAND>     Get input from a command
+  -C <COMMAND>     Get input from a command (repeatable, '-' reads stdin)
+  -R <RESTART>     Restart commands that exit: always, on-failure or never [default: never]
+  -p <FORMAT>      Parse input lines as: json, logfmt or raw [default: raw]
+  -F <PATH>        Follow a file, glob patterns allowed (repeatable)
+  -n <LINES>       Read the last LINES of followed files, 'all' for everything [default: 0]
+  -M <REGEX>       Lines matching it continue the event before them, e.g. '^\\s'
   -f <FILE>        Input configuration file (overrides CLI arguments)
   -o <OUTPUT_PATH> Specify the output path for matched patterns
+  -T <TEMPLATE>    Output file names, with {date}, {container} and {id} [default: {container}.txt]
+  -Z <SIZE>        Rotate the output files at SIZE, e.g. 10M
+  -I <INTERVAL>    Rotate the output files every INTERVAL, e.g. 1h
+  -z               Compress the rotated output files with gzip
+  -w <FILE>        Record every input line, with its timing, to FILE
+  -P <FILE>        Replay a recording instead of reading the inputs
+  -S <SPEED>       Replay speed: a factor like 2 or 10, or 'step' [default: 1]
+  -L <FILE>        Where the session is saved with 'S' [default: logss-session.yaml]
+  -b               Save the lines of the buffers with the session
+  -X <FORMAT>      Format of the buffers exported with 'e': text, jsonl or ansi [default: text]
   -r <RENDER>      Define render speed in milliseconds [default: 100]
   -t <THREADS>     Number of threads per container for triggers [default: 1]
+  -Q <SIZE>        Number of triggers that can wait per container [default: 100]
+  -O <OVERFLOW>    When the trigger queue is full: drop, coalesce or block [default: drop]
   -V               Start in vertical view mode
   -h               Print help
 ";
 
-#[derive(Debug, Serialize, Deserialize, PartialEq)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
 pub struct LocalContainer {
+    #[serde(default)]
     pub re: String,
+    /// Boolean combination of regexes used instead of `re`, e.g. `ERROR and not timeout`
+    pub expr: Option<String>,
     pub trigger: Option<String>,
+    /// How the line is handed to the trigger: safe, raw or stdin
+    pub trigger_input: Option<TriggerInput>,
     pub timeout: Option<u64>,
+    /// Regex that the name of the source must match
+    pub source: Option<String>,
+    /// Lines matching any of these regexes are left out of the container
+    pub exclude: Option<Vec<String>>,
+    /// Raw lines shown before each match
+    pub before: Option<usize>,
+    /// Raw lines shown after each match
+    pub after: Option<usize>,
+    /// Raw lines shown before and after each match, unless `before` or `after` are set
+    pub context: Option<usize>,
+    /// Colors of the named capture groups of `re`
+    pub colors: Option<BTreeMap<String, String>>,
+    /// Predicate on the fields of structured lines, e.g. `http.status >= 500`
+    pub when: Option<String>,
+    /// Fields of structured lines shown instead of the whole line
+    pub columns: Option<Vec<String>>,
+    /// Capture group (name or index) or field holding a number to plot
+    pub value: Option<String>,
+    /// Alerts that run the trigger once instead of on every match
+    pub alerts: Option<Vec<AlertRule>>,
+    /// Built-in actions run along with the trigger
+    pub actions: Option<Vec<ActionRule>>,
+    /// Number of triggers that can wait, overrides the global one
+    pub queue: Option<usize>,
+    /// When the trigger queue is full: drop, coalesce or block
+    pub overflow: Option<Overflow>,
+    /// Color of the title and the matches, by default the one of its position
+    pub color: Option<String>,
+    /// Start hidden
+    pub hide: Option<bool>,
+    /// Lines shown when starting, as saved with the session
+    pub buffer: Option<Vec<String>>,
+}
+
+/// What to do when the command of a source exits.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Restart {
+    Always,
+    OnFailure,
+    #[default]
+    Never,
+}
+
+impl FromStr for Restart {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "always" => Ok(Self::Always),
+            "on-failure" => Ok(Self::OnFailure),
+            "never" => Ok(Self::Never),
+            _ => Err(format!("`{s}` isn't a valid restart policy")),
+        }
+    }
+}
+
+/// An input source and the name used to tag its lines.
+///
+/// A source either runs a `command` or follows a file (`follow`).
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LocalSource {
+    pub name: Option<String>,
+    #[serde(default)]
+    pub command: Vec<String>,
+    /// Path (or glob pattern) of the file(s) to follow
+    pub follow: Option<String>,
+    /// Read followed files from the beginning
+    pub from_start: Option<bool>,
+    /// Read the last lines of followed files
+    pub lines: Option<usize>,
+    /// Restart policy of the command
+    pub restart: Option<Restart>,
+    /// Format of the lines, overrides the global one
+    pub format: Option<Format>,
+    /// How structured lines are displayed, overrides the global one
+    pub template: Option<String>,
+    /// How lines are grouped into events, overrides the global rule
+    pub multiline: Option<MultilineRule>,
 }
 
 impl FromStr for LocalContainer {
@@ -60,23 +181,114 @@ impl FromStr for LocalContainer {
             re,
             trigger,
             timeout,
+            ..Default::default()
         })
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
 pub struct Args {
     pub containers: Vec<LocalContainer>,
+    /// Lines matching any of these regexes are hidden everywhere
+    #[serde(default)]
+    pub exclude: Vec<String>,
     pub exit: Option<bool>,
     pub vertical: Option<bool>,
     pub single: Option<bool>,
+    /// Start in the raw view
+    pub raw: Option<bool>,
+    /// Start zoomed into the container with this id
+    pub zoom: Option<u8>,
+    pub wrap: Option<bool>,
+    /// Show multi-line events whole
+    pub unfold: Option<bool>,
     pub render: Option<u64>,
     pub threads: Option<u64>,
+    /// Number of triggers that can wait per container
+    pub queue: Option<usize>,
+    /// When the trigger queue is full
+    pub overflow: Option<Overflow>,
     pub command: Option<Vec<String>>,
+    #[serde(default)]
+    pub sources: Vec<LocalSource>,
+    /// Format of the input lines
+    pub format: Option<Format>,
+    /// How structured lines are displayed, `{path}` is replaced by the field value
+    pub template: Option<String>,
+    /// How the lines of every source are grouped into events
+    pub multiline: Option<MultilineRule>,
     pub output: Option<std::path::PathBuf>,
+    /// Name of the output files, with `{date}`, `{container}` and `{id}`
+    pub output_template: Option<String>,
+    /// When the output files are rotated
+    pub rotate: Option<RotateRule>,
+    /// Every input line is recorded to this file
+    pub record: Option<std::path::PathBuf>,
+    /// Recording replayed instead of reading the sources
+    pub replay: Option<std::path::PathBuf>,
+    /// How fast the recording is replayed: a factor like `10` or `step`
+    pub speed: Option<Speed>,
+    /// Where the session is saved [default: logss-session.yaml]
+    pub session: Option<std::path::PathBuf>,
+    /// Save the lines of the buffers with the session
+    pub session_buffers: Option<bool>,
+    /// Lines of the raw view shown when starting, as saved with the session
+    pub raw_buffer: Option<Vec<String>>,
+    /// Where the buffers are exported with 'e' [default: .]
+    pub export_dir: Option<std::path::PathBuf>,
+    /// How the buffers are exported: text, jsonl or ansi
+    pub export_format: Option<ExportFormat>,
     pub config_file: Option<std::path::PathBuf>,
 }
 
+impl Args {
+    /// Returns every input source, `command` first, each one with a unique name.
+    ///
+    /// Unnamed sources are named after the program they run or the file they follow.
+    pub fn input_sources(&self) -> Vec<LocalSource> {
+        let mut ret: Vec<LocalSource> = vec![];
+        let command = self.command.iter().map(|command| LocalSource {
+            command: command.clone(),
+            ..Default::default()
+        });
+        for source in command.chain(self.sources.iter().cloned()) {
+            // Each followed file is a source on its own
+            let expanded = match &source.follow {
+                Some(pattern) => expand_follow(pattern)
+                    .into_iter()
+                    .map(|follow| LocalSource {
+                        follow: Some(follow),
+                        ..source.clone()
+                    })
+                    .collect(),
+                None => vec![source],
+            };
+            for mut source in expanded {
+                let base = match (&source.name, &source.follow) {
+                    (Some(name), _) => name.as_str(),
+                    (None, Some(follow)) => follow_name(follow),
+                    (None, None) => match source.command.first().map(String::as_str) {
+                        None | Some("-") => "stdin",
+                        Some(cmd) => Path::new(cmd)
+                            .file_name()
+                            .and_then(|n| n.to_str())
+                            .unwrap_or(cmd),
+                    },
+                };
+                let mut name = base.to_string();
+                let mut n = 1;
+                while ret.iter().any(|s| s.name.as_ref() == Some(&name)) {
+                    n += 1;
+                    name = format!("{base}#{n}");
+                }
+                source.name = Some(name);
+                ret.push(source);
+            }
+        }
+        ret
+    }
+}
+
 pub fn parse_args() -> Args {
     match parser() {
         Ok(v) => v,
@@ -96,27 +308,82 @@ fn parser() -> Result<Args, Box<dyn std::error::Error>> {
         std::process::exit(0);
     }
 
+    let restart: Option<Restart> = pargs.opt_value_from_str("-R")?;
+    let mut sources: Vec<LocalSource> = pargs
+        .values_from_fn("-C", parse_cmd)?
+        .into_iter()
+        .map(|command| LocalSource {
+            command,
+            restart,
+            ..Default::default()
+        })
+        .collect();
+    let lines = pargs.opt_value_from_fn("-n", parse_lines)?;
+    for follow in pargs.values_from_str::<_, String>("-F")? {
+        sources.push(LocalSource {
+            follow: Some(follow),
+            from_start: lines.map(|l| l.is_none()),
+            lines: lines.flatten(),
+            ..Default::default()
+        });
+    }
+    let mut containers: Vec<LocalContainer> = pargs.values_from_str("-c")?;
+    for expr in pargs.values_from_str::<_, String>("-E")? {
+        containers.push(LocalContainer {
+            expr: Some(expr),
+            ..Default::default()
+        });
+    }
     let mut args = Args {
-        containers: pargs.values_from_str("-c")?,
-        command: pargs.opt_value_from_fn("-C", parse_cmd)?,
+        containers,
+        exclude: pargs.values_from_str("-x")?,
+        command: None,
+        sources,
+        format: pargs.opt_value_from_str("-p")?,
+        template: None,
+        multiline: pargs
+            .opt_value_from_str::<_, String>("-M")?
+            .map(|continuation| MultilineRule {
+                continuation: Some(continuation),
+                ..Default::default()
+            }),
         config_file: pargs.opt_value_from_os_str("-f", parse_path)?,
         output: pargs.opt_value_from_os_str("-o", validate_path)?,
+        output_template: pargs.opt_value_from_str("-T")?,
+        rotate: Some(RotateRule {
+            size: pargs.opt_value_from_str("-Z")?,
+            every: pargs.opt_value_from_str("-I")?,
+            gzip: pargs.contains("-z").then_some(true),
+        })
+        .filter(|rule| !rule.is_empty()),
+        record: pargs.opt_value_from_os_str("-w", parse_path)?,
+        replay: pargs.opt_value_from_os_str("-P", parse_path)?,
+        speed: pargs.opt_value_from_str("-S")?,
+        session: pargs.opt_value_from_os_str("-L", parse_path)?,
+        session_buffers: pargs.contains("-b").then_some(true),
+        raw_buffer: None,
+        export_dir: None,
+        export_format: pargs.opt_value_from_str("-X")?,
         exit: pargs.contains("-e").then_some(true),
         single: pargs.contains("-s").then_some(true),
         vertical: pargs.contains("-V").then_some(true),
+        raw: None,
+        zoom: None,
+        wrap: None,
+        unfold: None,
         render: pargs
             .opt_value_from_fn("-r", render_in_range)?
             .unwrap_or(Some(100)),
         threads: pargs
             .opt_value_from_fn("-t", render_in_range)?
             .unwrap_or(Some(4)),
+        queue: pargs.opt_value_from_fn("-Q", parse_queue)?,
+        overflow: pargs.opt_value_from_str("-O")?,
     };
 
     let render = args.render;
-
-    if !validate_regex(&args.containers) {
-        std::process::exit(1);
-    }
+    // Any configuration can be recorded or replayed
+    let (record, replay, speed) = (args.record.take(), args.replay.take(), args.speed);
 
     // It's up to the caller what to do with the remaining arguments.
     let remaining = pargs.finish();
@@ -131,6 +398,17 @@ fn parser() -> Result<Args, Box<dyn std::error::Error>> {
     if args.render.is_none() {
         args.render = render;
     }
+    args.record = record.or(args.record);
+    args.replay = replay.or(args.replay);
+    args.speed = speed.or(args.speed);
+
+    if !validate_patterns(&args.exclude)
+        || !validate_multiline(&args)
+        || !validate_output(&args)
+        || !validate_containers(&args.containers)
+    {
+        std::process::exit(1);
+    }
 
     Ok(args)
 }
@@ -143,10 +421,134 @@ fn parse_yaml(config_file: std::path::PathBuf) -> Result<Args, Box<dyn std::erro
 
 fn validate_regex(containers: &Vec<LocalContainer>) -> bool {
     for c in containers {
-        if Regex::new(&c.re).is_err() {
+        let source_is_err = c.source.as_ref().is_some_and(|s| Regex::new(s).is_err());
+        if Regex::new(&c.re).is_err() || source_is_err {
             eprintln!("Error: Failed to parse regexp '{c:?}'.");
             return false;
         }
+        if !validate_patterns(c.exclude.iter().flatten()) {
+            return false;
+        }
+    }
+    true
+}
+
+fn validate_patterns<'a>(patterns: impl IntoIterator<Item = &'a String>) -> bool {
+    for pattern in patterns {
+        if Regex::new(pattern).is_err() {
+            eprintln!("Error: Failed to parse exclude regexp '{pattern}'.");
+            return false;
+        }
+    }
+    true
+}
+
+fn validate_multiline(args: &Args) -> bool {
+    let rules = args.sources.iter().map(|s| &s.multiline);
+    for rule in [&args.multiline].into_iter().chain(rules).flatten() {
+        if let Err(e) = Multiline::new(rule) {
+            eprintln!("Error: Failed to parse multiline rule '{rule:?}': {e}.");
+            return false;
+        }
+    }
+    true
+}
+
+fn validate_output(args: &Args) -> bool {
+    if let Some(Err(e)) = args.output_template.as_deref().map(validate_template) {
+        eprintln!("Error: Failed to parse output template: {e}.");
+        return false;
+    }
+    true
+}
+
+fn validate_containers(containers: &Vec<LocalContainer>) -> bool {
+    validate_regex(containers)
+        && validate_colors(containers)
+        && validate_when(containers)
+        && validate_expr(containers)
+        && validate_alerts(containers)
+        && validate_actions(containers)
+        && validate_queue(containers)
+}
+
+fn validate_queue(containers: &Vec<LocalContainer>) -> bool {
+    for c in containers {
+        if c.queue == Some(0) {
+            eprintln!("Error: The trigger queue of '{}' can't be empty.", c.re);
+            return false;
+        }
+    }
+    true
+}
+
+fn validate_expr(containers: &Vec<LocalContainer>) -> bool {
+    for c in containers {
+        let Some(expr) = &c.expr else {
+            continue;
+        };
+        if !c.re.is_empty() {
+            eprintln!("Error: Container '{expr}' can't have both `re` and `expr`.");
+            return false;
+        }
+        if let Err(e) = expr.parse::<Expr>() {
+            eprintln!("Error: Failed to parse expression '{expr}': {e}.");
+            return false;
+        }
+    }
+    true
+}
+
+fn validate_actions(containers: &Vec<LocalContainer>) -> bool {
+    for c in containers {
+        for rule in c.actions.iter().flatten() {
+            if let Err(e) = rule.validate() {
+                eprintln!("Error: Failed to parse action '{rule:?}': {e}.");
+                return false;
+            }
+        }
+    }
+    true
+}
+
+fn validate_alerts(containers: &Vec<LocalContainer>) -> bool {
+    for c in containers {
+        for rule in c.alerts.iter().flatten() {
+            if let Err(e) = Alert::new(rule, Instant::now()) {
+                eprintln!("Error: Failed to parse alert '{rule:?}': {e}.");
+                return false;
+            }
+        }
+    }
+    true
+}
+
+fn validate_when(containers: &Vec<LocalContainer>) -> bool {
+    for c in containers {
+        if let Some(Err(e)) = c.when.as_ref().map(|w| w.parse::<Predicate>()) {
+            eprintln!(
+                "Error: Failed to parse predicate '{}': {e}.",
+                c.when.clone().unwrap_or_default()
+            );
+            return false;
+        }
+    }
+    true
+}
+
+fn validate_colors(containers: &Vec<LocalContainer>) -> bool {
+    for c in containers {
+        for color in c
+            .colors
+            .iter()
+            .flat_map(|colors| colors.values())
+            .chain(&c.color)
+        {
+            if Color::from_str(color).is_err() {
+                eprintln!("Error: Failed to parse color '{color}'.");
+                return false;
+            }
+        }
     }
     true
 }
@@ -180,8 +582,142 @@ fn validate_path(s: &std::ffi::OsStr) -> Result<std::path::PathBuf, String> {
     }
 }
 
-fn parse_cmd(s: &str) -> Result<Vec<String>, Box<dyn std::error::Error>> {
-    Ok(s.split_whitespace().map(str::to_string).collect())
+/// Splits a command line into its arguments following POSIX shell rules.
+///
+/// Single quotes keep everything literal, double quotes allow `\` escapes and
+/// environment variable expansion (`$VAR` or `${VAR}`), as does unquoted text.
+fn parse_cmd(s: &str) -> Result<Vec<String>, String> {
+    let mut args = vec![];
+    let mut arg = String::new();
+    // Tells empty quoted arguments apart from no argument at all
+    let mut in_arg = false;
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {
+                if in_arg {
+                    args.push(std::mem::take(&mut arg));
+                    in_arg = false;
+                }
+            }
+            '\'' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('\'') => break,
+                        Some(c) => arg.push(c),
+                        None => return Err("unterminated single quote".to_string()),
+                    }
+                }
+            }
+            '"' => {
+                in_arg = true;
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(c @ ('"' | '\\' | '$' | '`')) => arg.push(c),
+                            Some('\n') => {}
+                            Some(c) => {
+                                arg.push('\\');
+                                arg.push(c);
+                            }
+                            None => return Err("unterminated double quote".to_string()),
+                        },
+                        Some('$') => match read_var(&mut chars)? {
+                            Some(value) => arg.push_str(&value),
+                            None => arg.push('$'),
+                        },
+                        Some(c) => arg.push(c),
+                        None => return Err("unterminated double quote".to_string()),
+                    }
+                }
+            }
+            '\\' => {
+                in_arg = true;
+                match chars.next() {
+                    Some('\n') => {}
+                    Some(c) => arg.push(c),
+                    None => return Err("unterminated escape".to_string()),
+                }
+            }
+            '$' => match read_var(&mut chars)? {
+                // Unquoted values are split into several arguments
+                Some(value) => {
+                    for c in value.chars() {
+                        if !c.is_whitespace() {
+                            in_arg = true;
+                            arg.push(c);
+                        } else if in_arg {
+                            args.push(std::mem::take(&mut arg));
+                            in_arg = false;
+                        }
+                    }
+                }
+                None => {
+                    in_arg = true;
+                    arg.push('$');
+                }
+            },
+            c => {
+                in_arg = true;
+                arg.push(c);
+            }
+        }
+    }
+    if in_arg {
+        args.push(arg);
+    }
+
+    if args.is_empty() {
+        return Err("empty command".to_string());
+    }
+    Ok(args)
+}
+
+/// Reads the variable name following a `$` and returns its value.
+///
+/// Unset variables are empty and `None` means there was no name, so the `$` is literal.
+fn read_var(chars: &mut std::iter::Peekable<std::str::Chars>) -> Result<Option<String>, String> {
+    let is_name = |c: &char| c.is_ascii_alphanumeric() || *c == '_';
+    let mut name = String::new();
+    if chars.next_if_eq(&'{').is_some() {
+        loop {
+            match chars.next() {
+                Some('}') => break,
+                Some(c) if is_name(&c) => name.push(c),
+                _ => return Err("bad variable substitution".to_string()),
+            }
+        }
+    } else {
+        while let Some(c) = chars.next_if(is_name) {
+            name.push(c);
+        }
+        if name.is_empty() {
+            return Ok(None);
+        }
+    }
+    Ok(Some(std::env::var(name).unwrap_or_default()))
+}
+
+/// Parses the number of lines to read from followed files, `None` means all of them.
+fn parse_lines(s: &str) -> Result<Option<usize>, String> {
+    if s == "all" {
+        return Ok(None);
+    }
+    let lines: usize = s
+        .parse()
+        .map_err(|_| format!("`{s}` isn't a valid number of lines"))?;
+
+    Ok(Some(lines))
+}
+
+fn parse_queue(s: &str) -> Result<usize, String> {
+    match s.parse() {
+        Ok(0) | Err(_) => Err(format!("`{s}` isn't a valid queue size")),
+        Ok(size) => Ok(size),
+    }
 }
 
 fn render_in_range(s: &str) -> Result<Option<u64>, String> {
@@ -200,9 +736,11 @@ mod tests {
         ffi::OsStr,
         fs::{remove_dir_all, DirBuilder},
         path::PathBuf,
+        time::Duration,
     };
 
     use super::*;
+    use crate::output::{Interval, Size};
     #[test]
     fn test_render_in_range() {
         assert_eq!(render_in_range("30"), Ok(Some(30)));
@@ -214,6 +752,7 @@ mod tests {
             re: "a".to_string(),
             trigger: None,
             timeout: None,
+            ..Default::default()
         }];
         assert!(validate_regex(&c));
 
@@ -221,10 +760,392 @@ mod tests {
             re: "*".to_string(),
             trigger: None,
             timeout: None,
+            ..Default::default()
+        }];
+        assert!(!validate_regex(&c));
+    }
+
+    #[test]
+    fn test_validate_source_regex() {
+        let c = vec![LocalContainer {
+            re: "a".to_string(),
+            source: Some("api|web".to_string()),
+            ..Default::default()
+        }];
+        assert!(validate_regex(&c));
+
+        let c = vec![LocalContainer {
+            re: "a".to_string(),
+            source: Some("*".to_string()),
+            ..Default::default()
         }];
         assert!(!validate_regex(&c));
     }
 
+    #[test]
+    fn test_input_sources() {
+        let mut args = Args::default();
+        assert!(args.input_sources().is_empty());
+
+        let cmd = |c: &str| c.split(' ').map(str::to_string).collect::<Vec<String>>();
+        args.command = Some(cmd("/usr/bin/tail -F a.log"));
+        args.sources = vec![
+            LocalSource {
+                command: cmd("tail -F b.log"),
+                ..Default::default()
+            },
+            LocalSource {
+                name: Some("api".to_string()),
+                command: cmd("kubectl logs api"),
+                ..Default::default()
+            },
+            LocalSource {
+                command: cmd("-"),
+                ..Default::default()
+            },
+            LocalSource {
+                follow: Some("/var/log/syslog".to_string()),
+                ..Default::default()
+            },
+        ];
+        let names: Vec<Option<String>> = args.input_sources().into_iter().map(|s| s.name).collect();
+        assert_eq!(
+            names,
+            vec![
+                Some("tail".to_string()),
+                Some("tail#2".to_string()),
+                Some("api".to_string()),
+                Some("stdin".to_string()),
+                Some("syslog".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_cmd() {
+        let cmd = |v: &[&str]| Ok(v.iter().map(|s| s.to_string()).collect::<Vec<String>>());
+        assert_eq!(parse_cmd("tail -F  file"), cmd(&["tail", "-F", "file"]));
+        assert_eq!(
+            parse_cmd("grep 'foo bar' file"),
+            cmd(&["grep", "foo bar", "file"])
+        );
+        assert_eq!(
+            parse_cmd(r#"grep "foo \"bar\"" 'it'\''s'"#),
+            cmd(&["grep", r#"foo "bar""#, "it's"])
+        );
+        assert_eq!(
+            parse_cmd(r"echo a\ b \$HOME"),
+            cmd(&["echo", "a b", "$HOME"])
+        );
+        assert_eq!(parse_cmd(r#"echo "" '' x"#), cmd(&["echo", "", "", "x"]));
+        assert_eq!(parse_cmd(r#"echo "a\b""#), cmd(&["echo", r"a\b"]));
+        assert_eq!(parse_cmd("echo a$"), cmd(&["echo", "a$"]));
+    }
+
+    #[test]
+    fn test_parse_cmd_env() {
+        std::env::set_var("LOGSS_TEST_VAR", "some value");
+        let cmd = |v: &[&str]| Ok(v.iter().map(|s| s.to_string()).collect::<Vec<String>>());
+        assert_eq!(
+            parse_cmd("echo $LOGSS_TEST_VAR"),
+            cmd(&["echo", "some", "value"])
+        );
+        assert_eq!(
+            parse_cmd("echo x$LOGSS_TEST_VAR"),
+            cmd(&["echo", "xsome", "value"])
+        );
+        assert_eq!(
+            parse_cmd(r#"echo "${LOGSS_TEST_VAR}!" '$LOGSS_TEST_VAR'"#),
+            cmd(&["echo", "some value!", "$LOGSS_TEST_VAR"])
+        );
+        assert_eq!(parse_cmd("echo $LOGSS_UNSET_VAR."), cmd(&["echo", "."]));
+        assert_eq!(
+            parse_cmd("echo ${LOGSS"),
+            Err("bad variable substitution".to_string())
+        );
+    }
+
+    #[test]
+    fn test_parse_cmd_errors() {
+        assert_eq!(parse_cmd(""), Err("empty command".to_string()));
+        assert_eq!(parse_cmd("   "), Err("empty command".to_string()));
+        assert_eq!(
+            parse_cmd("grep 'foo"),
+            Err("unterminated single quote".to_string())
+        );
+        assert_eq!(
+            parse_cmd("grep \"foo"),
+            Err("unterminated double quote".to_string())
+        );
+        assert_eq!(parse_cmd("grep \\"), Err("unterminated escape".to_string()));
+    }
+
+    #[test]
+    fn test_restart() {
+        assert_eq!("always".parse(), Ok(Restart::Always));
+        assert_eq!("on-failure".parse(), Ok(Restart::OnFailure));
+        assert_eq!("never".parse(), Ok(Restart::Never));
+        assert!("sometimes".parse::<Restart>().is_err());
+        let source: LocalSource =
+            serde_yaml::from_str("command: [kubectl, logs, api]\nrestart: on-failure").unwrap();
+        assert_eq!(source.restart, Some(Restart::OnFailure));
+    }
+
+    #[test]
+    fn test_parse_lines() {
+        assert_eq!(parse_lines("10"), Ok(Some(10)));
+        assert_eq!(parse_lines("all"), Ok(None));
+        assert!(parse_lines("some").is_err());
+    }
+
+    #[test]
+    fn test_parse_yaml_sources() {
+        let yaml = r#"
+containers:
+  - re: error
+    source: api
+  - when: level == "error"
+    columns: [level, msg]
+sources:
+  - name: api
+    command: [kubectl, logs, api]
+    format: json
+  - command: [kubectl, logs, web]
+    format: logfmt
+  - follow: /var/log/nginx/access.log
+    lines: 10
+"#;
+        let args: Args = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(args.containers[0].source, Some("api".to_string()));
+        assert_eq!(args.containers[1].re, "");
+        assert_eq!(
+            args.containers[1].when,
+            Some("level == \"error\"".to_string())
+        );
+        assert_eq!(
+            args.containers[1].columns,
+            Some(vec!["level".to_string(), "msg".to_string()])
+        );
+        assert_eq!(args.sources[0].format, Some(Format::Json));
+        assert_eq!(args.sources[1].format, Some(Format::Logfmt));
+        assert_eq!(args.sources.len(), 3);
+        assert_eq!(args.sources[2].lines, Some(10));
+        assert_eq!(args.sources[0].name, Some("api".to_string()));
+        assert_eq!(args.sources[1].name, None);
+        assert_eq!(args.input_sources()[1].name, Some("kubectl".to_string()));
+        assert_eq!(args.input_sources()[2].name, Some("access.log".to_string()));
+    }
+
+    #[test]
+    fn test_validate_colors() {
+        let mut c = vec![LocalContainer {
+            re: "(?P<level>ERROR)".to_string(),
+            colors: Some(BTreeMap::from([(
+                "level".to_string(),
+                "light-red".to_string(),
+            )])),
+            ..Default::default()
+        }];
+        assert!(validate_colors(&c));
+        c[0].colors = Some(BTreeMap::from([(
+            "level".to_string(),
+            "reddish".to_string(),
+        )]));
+        assert!(!validate_colors(&c));
+    }
+
+    #[test]
+    fn test_validate_when() {
+        let mut c = vec![LocalContainer {
+            when: Some("http.status >= 500".to_string()),
+            ..Default::default()
+        }];
+        assert!(validate_when(&c));
+        assert!(validate_containers(&c));
+        c[0].when = Some("http.status".to_string());
+        assert!(!validate_when(&c));
+        assert!(!validate_containers(&c));
+    }
+
+    #[test]
+    fn test_validate_expr() {
+        let yaml = r"
+containers:
+  - expr: (payment or checkout) and 5\d\d
+  - re: ERROR
+";
+        let mut args: Args = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            args.containers[0].expr,
+            Some(r"(payment or checkout) and 5\d\d".to_string())
+        );
+        assert!(validate_expr(&args.containers));
+        args.containers[1].expr = Some("not timeout".to_string());
+        assert!(!validate_expr(&args.containers));
+        args.containers[1].re = String::new();
+        assert!(validate_containers(&args.containers));
+        args.containers[1].expr = Some("ERROR and".to_string());
+        assert!(!validate_containers(&args.containers));
+    }
+
+    #[test]
+    fn test_validate_exclude() {
+        let yaml = "
+exclude: [healthz]
+containers:
+  - re: GET
+    exclude: [/metrics, /ping]
+";
+        let mut args: Args = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(args.exclude, vec!["healthz".to_string()]);
+        assert_eq!(args.containers[0].exclude.as_ref().unwrap().len(), 2);
+        assert!(validate_patterns(&args.exclude));
+        assert!(validate_containers(&args.containers));
+        args.containers[0].exclude = Some(vec!["(".to_string()]);
+        assert!(!validate_containers(&args.containers));
+        assert!(!validate_patterns(&["[".to_string()]));
+    }
+
+    #[test]
+    fn test_validate_multiline() {
+        let yaml = r"
+multiline:
+  continuation: ^\s
+sources:
+  - command: [python3, app.py]
+    multiline:
+      start: ^\d{4}-
+      max_lines: 100
+containers:
+  - re: Exception
+";
+        let mut args: Args = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            args.multiline.as_ref().unwrap().continuation.as_deref(),
+            Some(r"^\s")
+        );
+        assert_eq!(
+            args.sources[0].multiline.as_ref().unwrap().max_lines,
+            Some(100)
+        );
+        assert!(validate_multiline(&args));
+        args.sources[0].multiline = Some(MultilineRule::default());
+        assert!(!validate_multiline(&args));
+    }
+
+    #[test]
+    fn test_validate_output() {
+        let yaml = "
+output: logs
+output_template: '{date}-{container}-{id}.log'
+rotate:
+  size: 10M
+  every: 1h
+  gzip: true
+containers:
+  - re: ERROR
+";
+        let mut args: Args = serde_yaml::from_str(yaml).unwrap();
+        let rotate = args.rotate.clone().unwrap();
+        assert_eq!(rotate.size, Some(Size(10 << 20)));
+        assert_eq!(rotate.every, Some(Interval(Duration::from_secs(3600))));
+        assert_eq!(rotate.gzip, Some(true));
+        assert!(validate_output(&args));
+        args.output_template = Some("{name}.log".to_string());
+        assert!(!validate_output(&args));
+        assert!(serde_yaml::from_str::<Args>("rotate:\n  size: 0\n").is_err());
+    }
+
+    #[test]
+    fn test_replay() {
+        let yaml = "
+replay: incident.rec
+speed: 10
+containers:
+  - re: ERROR
+";
+        let args: Args = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(args.replay, Some("incident.rec".into()));
+        assert_eq!(args.speed, Some(Speed::Factor(10.0)));
+        let args: Args = serde_yaml::from_str("speed: step\ncontainers: []").unwrap();
+        assert_eq!(args.speed, Some(Speed::Step));
+        assert!(serde_yaml::from_str::<Args>("speed: -2\ncontainers: []").is_err());
+    }
+
+    #[test]
+    fn test_validate_queue() {
+        let yaml = "
+queue: 10
+overflow: block
+containers:
+  - re: ERROR
+    trigger: notify-send __line__
+    queue: 1
+    overflow: coalesce
+";
+        let mut args: Args = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(args.queue, Some(10));
+        assert_eq!(args.overflow, Some(Overflow::Block));
+        assert_eq!(args.containers[0].overflow, Some(Overflow::Coalesce));
+        assert!(validate_queue(&args.containers));
+        args.containers[0].queue = Some(0);
+        assert!(!validate_containers(&args.containers));
+        assert_eq!(parse_queue("5"), Ok(5));
+        assert!(parse_queue("0").is_err());
+        assert!(parse_queue("-1").is_err());
+    }
+
+    #[test]
+    fn test_validate_actions() {
+        let yaml = "
+containers:
+  - re: ERROR
+    actions:
+      - file: /tmp/errors.log
+        template: '{container} {line}'
+      - post: http://localhost:9000/hook
+        batch: 10
+        flush: 5
+        retries: 2
+      - fifo: /tmp/logss.fifo
+";
+        let mut args: Args = serde_yaml::from_str(yaml).unwrap();
+        let actions = args.containers[0].actions.clone().unwrap();
+        assert_eq!(actions[0].template, Some("{container} {line}".to_string()));
+        assert_eq!(actions[1].batch, Some(10));
+        assert_eq!(actions[1].retries, Some(2));
+        assert!(actions[2].fifo.is_some());
+        assert!(validate_actions(&args.containers));
+        args.containers[0].actions = Some(vec![ActionRule {
+            post: Some("https://localhost/".to_string()),
+            ..Default::default()
+        }]);
+        assert!(!validate_actions(&args.containers));
+        assert!(!validate_containers(&args.containers));
+    }
+
+    #[test]
+    fn test_validate_alerts() {
+        let yaml = "
+containers:
+  - re: ERROR
+    alerts:
+      - above: 20
+        within: 60
+        cooldown: 300
+      - absent: 300
+";
+        let mut args: Args = serde_yaml::from_str(yaml).unwrap();
+        let alerts = args.containers[0].alerts.clone().unwrap();
+        assert_eq!(alerts[0].above, Some(20));
+        assert_eq!(alerts[0].cooldown, Some(300));
+        assert_eq!(alerts[1].absent, Some(300));
+        assert!(validate_alerts(&args.containers));
+        args.containers[0].alerts = Some(vec![AlertRule::default()]);
+        assert!(!validate_alerts(&args.containers));
+        assert!(!validate_containers(&args.containers));
+    }
+
     #[test]
     fn test_validate_path_non_valid() {
         let resp = Err("non_valid_path is not a valid path".to_string());
@@ -265,26 +1186,31 @@ mod tests {
                     re: "to".to_string(),
                     trigger: Some("echo $(date) >> /tmp/dates.txt".to_string()),
                     timeout: Some(1),
+                    ..Default::default()
                 },
                 LocalContainer {
                     re: "be".to_string(),
                     trigger: Some("echo '__line__' >> /tmp/match_lines.txt".to_string()),
                     timeout: Some(1),
+                    ..Default::default()
                 },
                 LocalContainer {
                     re: "or".to_string(),
                     trigger: None,
                     timeout: Some(1),
+                    ..Default::default()
                 },
                 LocalContainer {
                     re: "not".to_string(),
                     trigger: None,
                     timeout: Some(1),
+                    ..Default::default()
                 },
                 LocalContainer {
                     re: "to.*be".to_string(),
                     trigger: None,
                     timeout: Some(1),
+                    ..Default::default()
                 },
             ]
         );
@@ -82,6 +82,20 @@ where
         cb
     }
 
+    /// Returns the number of elements pushed since the last reset
+    pub fn written(&self) -> usize {
+        self.write_index
+    }
+
+    /// Returns the element pushed `n` pushes ago, 0 being the newest
+    pub fn newest(&self, n: usize) -> Option<&T> {
+        if n >= self.len() {
+            return None;
+        }
+        self.buffer
+            .get((self.write_index - 1 - n) % self.capacity())
+    }
+
     pub fn reset(&mut self) {
         self.buffer.clear();
         self.write_index = 0;
@@ -124,4 +138,21 @@ mod tests {
         let cb2 = cb.ordered_clone();
         assert_eq!(cb2.buffer, vec![2, 3, 4]);
     }
+
+    #[test]
+    fn circular_buffer_newest() {
+        let mut cb: CircularBuffer<i32> = CircularBuffer::new(3);
+        assert_eq!(cb.newest(0), None);
+        cb.push(1);
+        cb.push(2);
+        assert_eq!(cb.newest(0), Some(&2));
+        assert_eq!(cb.newest(1), Some(&1));
+        assert_eq!(cb.newest(2), None);
+        cb.push(3);
+        cb.push(4);
+        assert_eq!(cb.written(), 4);
+        assert_eq!(cb.newest(0), Some(&4));
+        assert_eq!(cb.newest(2), Some(&2));
+        assert_eq!(cb.newest(3), None);
+    }
 }
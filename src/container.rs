@@ -1,27 +1,38 @@
 use std::{
-    fs::{File, OpenOptions},
-    io::Write,
-    path::{Path, PathBuf},
-    process::Command,
-    time::Duration,
+    collections::{BTreeMap, VecDeque},
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc::Sender,
+    time::{Duration, Instant},
 };
 
 use anyhow::Result;
 use ratatui::{
-    layout::Rect,
+    layout::{Constraint, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
     widgets::{Block, Borders, Paragraph, Wrap},
     Frame,
 };
-use regex::Regex;
+use regex::{Captures, Regex};
 use slug;
-use threadpool::ThreadPool;
-use wait_timeout::ChildExt;
 
 use crate::{
+    actions::{Action, ActionRule},
+    alerts::{Alert, AlertRule},
+    args::LocalContainer,
     cb::CircularBuffer,
+    context::Context,
+    expr::Expr,
+    fields::{as_number, lookup, parse_number, render_columns, Predicate},
+    multiline::unfold,
+    output::{OutputFile, RotateRule},
+    queue::{Overflow, TriggerJob, TriggerQueue, TRIGGER_QUEUE},
+    search::{line_text, Search},
+    series::{Series, SPARKLINE_HEIGHT},
     states::{ContainerState, ScrollDirection},
+    trigger::{TriggerEvent, TriggerInput, TriggerOutcome},
+    tstdin::InputLine,
 };
 
 pub const CONTAINER_BUFFER: usize = 1024;
@@ -44,14 +55,54 @@ pub struct Container<'a> {
     /// matching text
     pub text: String,
     pub re: Regex,
+    /// only lines from matching sources are considered
+    pub source: Option<Regex>,
+    /// colors of the named capture groups
+    pub colors: BTreeMap<String, Color>,
+    /// predicate on the fields of structured lines
+    pub when: Option<Predicate>,
+    /// lines matching any of these are left out
+    pub exclude: Vec<Regex>,
+    /// boolean combination of patterns used instead of `re`
+    pub expr: Option<Expr>,
+    /// raw lines shown around the matches
+    pub context: Context,
+    /// raw positions of the newest lines from the source, the newest last
+    seen: VecDeque<usize>,
+    /// lines seen from the source
+    seen_count: usize,
+    /// fields shown instead of the whole structured line
+    pub columns: Vec<String>,
+    /// numeric values of the matching lines
+    pub series: Option<Series>,
+    /// alerts that run the trigger instead of every match
+    pub alerts: Vec<Alert>,
     /// circular buffer with matching lines
     pub cb: CircularBuffer<Line<'a>>,
+    /// lines, unfolded, pushed out of the buffer
+    evicted_lines: usize,
+    /// [`Container::first_line`] when the scroll was last updated
+    scroll_first: usize,
     pub id: u8,
     pub state: ContainerState,
-    pub file: Option<File>,
+    /// file the matching lines are written to
+    pub output: Option<OutputFile>,
+    /// last failure to write to the output file, until it is reported
+    pub output_error: Option<String>,
     pub trigger: Option<String>,
+    /// how the line is handed to the trigger
+    pub trigger_input: TriggerInput,
     pub timeout: u64,
-    pub thread_pool: Option<ThreadPool>,
+    /// triggers waiting to run, `None` without a trigger
+    pub queue: Option<TriggerQueue>,
+    /// where the outcome of the triggers is sent
+    pub results: Option<Sender<TriggerOutcome>>,
+    /// built-in actions run along with the trigger
+    pub actions: Vec<Action>,
+    /// search whose hits are highlighted
+    pub search: Option<Search>,
+    /// definition the container was created from
+    pub config: LocalContainer,
 }
 
 impl<'a> Container<'a> {
@@ -63,21 +114,44 @@ impl<'a> Container<'a> {
         buffersize: usize,
     ) -> Self {
         let re = Regex::new(&text).unwrap();
-        let thread_pool = if threads > 0 {
-            Some(ThreadPool::new(threads as usize))
+        let queue = if trigger.is_some() && threads > 0 {
+            Some(TriggerQueue::new(
+                threads as usize,
+                TRIGGER_QUEUE,
+                Overflow::default(),
+            ))
         } else {
             None
         };
         Self {
             text: text.clone(),
             re,
+            source: None,
+            colors: BTreeMap::new(),
+            when: None,
+            exclude: vec![],
+            expr: None,
+            context: Context::default(),
+            seen: VecDeque::new(),
+            seen_count: 0,
+            config: LocalContainer::default(),
+            columns: vec![],
+            series: None,
+            alerts: vec![],
             cb: CircularBuffer::new(buffersize),
+            evicted_lines: 0,
+            scroll_first: 0,
             id: 0,
             state: ContainerState::default(),
-            file: None,
+            output: None,
+            output_error: None,
             trigger,
+            trigger_input: TriggerInput::default(),
             timeout,
-            thread_pool,
+            queue,
+            results: None,
+            actions: vec![],
+            search: None,
         }
     }
 
@@ -86,87 +160,479 @@ impl<'a> Container<'a> {
         Self {
             text: text.to_string(),
             re,
+            source: None,
+            colors: BTreeMap::new(),
+            when: None,
+            exclude: vec![],
+            expr: None,
+            context: Context::default(),
+            seen: VecDeque::new(),
+            seen_count: 0,
+            config: LocalContainer::default(),
+            columns: vec![],
+            series: None,
+            alerts: vec![],
             cb: CircularBuffer::new(CONTAINER_BUFFER),
+            evicted_lines: 0,
+            scroll_first: 0,
             id: 0,
             state: ContainerState::default(),
-            file: None,
+            output: None,
+            output_error: None,
             trigger: None,
+            trigger_input: TriggerInput::default(),
             timeout: 1,
-            thread_pool: None,
+            queue: None,
+            results: None,
+            actions: vec![],
+            search: None,
         }
     }
 
-    pub fn set_output_path(&mut self, output_path: PathBuf) -> Result<()> {
-        let file_name = format!(
-            "{}/{}.txt",
-            output_path.to_string_lossy(),
-            slug::slugify(self.text.clone())
-        );
-        let file_path = Path::new(&file_name);
-        self.file = Some(
-            OpenOptions::new()
-                .append(true)
-                .create(true)
-                .open(file_path)?,
-        );
+    /// Writes the matching lines to a file of the directory named after the template.
+    pub fn set_output_path(
+        &mut self,
+        output_path: PathBuf,
+        template: &str,
+        rotate: &RotateRule,
+    ) -> Result<()> {
+        let output = OutputFile::open(&output_path, template, self.id, &self.slug(), rotate)?;
+        self.output = Some(output);
+        Ok(())
+    }
+
+    /// Flushes and rotates the output file, returns the write failure to report if any.
+    pub fn tick_output(&mut self, now: Instant) -> Option<String> {
+        if let Some(output) = &mut self.output {
+            if let Err(e) = output.tick(now) {
+                self.output_error = Some(format!("{}: {e}", output.path().display()));
+            }
+        }
+        self.output_error.take()
+    }
+
+    pub fn set_source(&mut self, source: &str) -> Result<()> {
+        self.source = Some(Regex::new(source)?);
+        Ok(())
+    }
+
+    /// Sets the color of each named capture group
+    pub fn set_colors(&mut self, colors: &BTreeMap<String, String>) -> Result<()> {
+        for (name, color) in colors {
+            self.colors.insert(name.clone(), Color::from_str(color)?);
+        }
+        Ok(())
+    }
 
+    /// Matches the lines with an expression, `re` only highlights its patterns.
+    pub fn set_expr(&mut self, expr: &str) -> Result<()> {
+        let expr: Expr = expr.parse().map_err(anyhow::Error::msg)?;
+        self.re = expr.highlight();
+        self.text = expr.to_string();
+        self.expr = Some(expr);
         Ok(())
     }
 
+    pub fn set_when(&mut self, when: &str) -> Result<()> {
+        self.when = Some(when.parse().map_err(anyhow::Error::msg)?);
+        Ok(())
+    }
+
+    /// Adds a pattern of lines to leave out
+    pub fn add_exclude(&mut self, pattern: &str) -> Result<()> {
+        self.exclude.push(Regex::new(pattern)?);
+        Ok(())
+    }
+
+    /// Returns true if an exclude pattern matches the line
+    pub fn excludes(&self, line: &InputLine) -> bool {
+        self.exclude.iter().any(|re| re.is_match(&line.text))
+    }
+
+    /// Returns true if the line comes from a source the container looks at
+    pub fn from_source(&self, line: &InputLine) -> bool {
+        self.source
+            .as_ref()
+            .map_or(true, |source| source.is_match(&line.source))
+    }
+
+    /// Returns true if the line belongs in this container
+    pub fn matches(&self, line: &InputLine) -> bool {
+        if !self.from_source(line) {
+            return false;
+        }
+        if let Some(when) = &self.when {
+            match &line.fields {
+                Some(fields) if when.eval(fields) => (),
+                _ => return false,
+            }
+        }
+        match &self.expr {
+            Some(expr) => expr.is_match(&line.text),
+            None => self.re.is_match(&line.text),
+        }
+    }
+
+    /// What the container matches, fit for file names
+    pub fn slug(&self) -> String {
+        slug::slugify(self.description())
+    }
+
+    /// What the container matches, as shown in its title
+    pub fn description(&self) -> String {
+        let what = match &self.expr {
+            Some(expr) => expr.to_string(),
+            None => format!("'{}'", self.text),
+        };
+        match &self.when {
+            None => what,
+            Some(when) if self.text.is_empty() => when.to_string(),
+            Some(when) => format!("{what} if {when}"),
+        }
+    }
+
     fn process_line(&self, line: &str) -> Option<Line<'a>> {
         // TODO: maybe add smart time coloration?
-        if let Some(mat) = self.re.find(line) {
-            let start = mat.start();
-            let end = mat.end();
-
-            return Some(Line::from(vec![
-                Span::from(line[0..start].to_string()),
-                Span::styled(
-                    line[start..end].to_string(),
+        if self.expr.as_ref().is_some_and(|expr| !expr.is_match(line)) {
+            return None;
+        }
+        let mut spans = vec![];
+        let mut last = 0;
+        // Expressions can match without any of their patterns
+        let mut matched = self.expr.is_some();
+        for caps in self.re.captures_iter(line) {
+            matched = true;
+            let Some(mat) = caps.get(0) else { continue };
+            if mat.is_empty() {
+                continue;
+            }
+            spans.push(Span::from(line[last..mat.start()].to_string()));
+            spans.extend(self.match_spans(line, &caps));
+            last = mat.end();
+        }
+        if !matched {
+            return None;
+        }
+        spans.push(Span::from(line[last..].to_string()));
+        Some(Line::from(spans))
+    }
+
+    /// Colors a match, named capture groups with their own color if they have one.
+    fn match_spans(&self, line: &str, caps: &Captures) -> Vec<Span<'a>> {
+        let Some(mat) = caps.get(0) else {
+            return vec![];
+        };
+        let mut groups: Vec<(usize, usize, Color)> = self
+            .re
+            .capture_names()
+            .enumerate()
+            .filter_map(|(i, name)| {
+                let color = self.colors.get(name?)?;
+                let group = caps.get(i)?;
+                Some((group.start(), group.end(), *color))
+            })
+            .collect();
+        // Outer groups win over nested ones
+        groups.sort_by_key(|(start, end, _)| (*start, usize::MAX - end));
+
+        let mut spans = vec![];
+        let mut position = mat.start();
+        for (start, end, color) in groups {
+            if start < position || start == end {
+                continue;
+            }
+            if start > position {
+                spans.push(Span::styled(
+                    line[position..start].to_string(),
                     Style::default().fg(self.state.color),
-                ),
-                Span::from(line[end..].to_string()),
-            ]));
+                ));
+            }
+            spans.push(Span::styled(
+                line[start..end].to_string(),
+                Style::default().fg(color),
+            ));
+            position = end;
         }
-        None
+        if position < mat.end() || spans.is_empty() {
+            spans.push(Span::styled(
+                line[position..mat.end()].to_string(),
+                Style::default().fg(self.state.color),
+            ));
+        }
+        spans
     }
 
     pub fn push(&mut self, element: Line<'a>) {
+        let now = Instant::now();
         self.state.count += 1;
-        let _ = &self.cb.push(element);
+        self.state.rates.record(now);
+        for alert in self.alerts.iter_mut() {
+            alert.record(now);
+        }
+        self.push_line(element);
+    }
+
+    /// Pushes the line to the buffer, neither counted nor acted upon.
+    pub fn push_line(&mut self, line: Line<'a>) {
+        if self.cb.len() == self.cb.capacity() {
+            if let Some(oldest) = self.cb.newest(self.cb.len() - 1) {
+                self.evicted_lines += unfold(oldest, false).len();
+            }
+        }
+        self.cb.push(line);
+    }
+
+    /// Number of the first line shown, counting those pushed out of the buffer.
+    pub fn first_line(&self) -> usize {
+        if self.state.unfold {
+            self.evicted_lines
+        } else {
+            self.cb.written() - self.cb.len()
+        }
+    }
+
+    /// Takes note of the newest raw line, which comes from the source of the container.
+    ///
+    /// The context is made of these lines only, not of those of other sources.
+    pub fn see(&mut self, raw: &CircularBuffer<Line<'a>>) {
+        let Some(position) = raw.written().checked_sub(1) else {
+            return;
+        };
+        self.seen.push_back(position);
+        if self.seen.len() > self.context.before + 1 {
+            self.seen.pop_front();
+        }
+        self.seen_count += 1;
+    }
+
+    /// Pushes the lines seen before the match, the newest line seen, that are not shown yet.
+    ///
+    /// A `--` line sets them apart from the lines shown before, as `grep` does.
+    pub fn push_before(&mut self, raw: &CircularBuffer<Line<'a>>) {
+        let Some(position) = self.seen_count.checked_sub(1) else {
+            return;
+        };
+        let (before, apart) = self.context.on_match(position);
+        if apart {
+            self.push_line(Line::styled("--", Style::default().fg(Color::DarkGray)));
+        }
+        let newest = raw.written().saturating_sub(1);
+        for p in before {
+            let line = self
+                .seen
+                .len()
+                .checked_sub(1 + position - p)
+                .and_then(|i| raw.newest(newest.checked_sub(self.seen[i])?));
+            if let Some(line) = line {
+                self.push_context(line);
+            }
+        }
+    }
+
+    /// Pushes the newest line seen, which did not match, if it is shown after a match.
+    pub fn push_after(&mut self, raw: &CircularBuffer<Line<'a>>) {
+        let Some(position) = self.seen_count.checked_sub(1) else {
+            return;
+        };
+        if self.context.on_other(position) {
+            if let Some(line) = raw.newest(0) {
+                self.push_context(line);
+            }
+        }
+    }
+
+    /// Pushes a dimmed raw line, not counted as a match.
+    fn push_context(&mut self, line: &Line) {
+        // A single span keeps the line breaks of multi-line events
+        self.push_line(
+            Line::from(Span::raw(line_text(line))).style(Style::default().fg(Color::DarkGray)),
+        );
+    }
+
+    /// Pushes a line saved with the session, highlighted but neither counted nor acted upon.
+    pub fn restore(&mut self, text: &str) {
+        let line = self
+            .process_line(text)
+            .unwrap_or_else(|| Line::from(Span::raw(text.to_string())));
+        self.push_line(line);
+    }
+
+    /// Definition of the container as it is now, to save the session.
+    pub fn session(&self, buffers: bool) -> LocalContainer {
+        let exclude: Vec<String> = self.exclude.iter().map(|re| re.to_string()).collect();
+        LocalContainer {
+            exclude: (!exclude.is_empty()).then_some(exclude),
+            color: Some(self.state.color.to_string()),
+            hide: self.state.hide.then_some(true),
+            buffer: buffers.then(|| {
+                self.cb
+                    .ordered_clone()
+                    .buffer
+                    .iter()
+                    .map(line_text)
+                    .collect()
+            }),
+            ..self.config.clone()
+        }
+    }
+
+    /// Pushes a line that visually separates what came before from what comes after.
+    ///
+    /// It is not counted as a match.
+    pub fn push_separator(&mut self, text: &str) {
+        self.push_line(Line::from(Span::styled(
+            format!("──── {text} ────"),
+            Style::default()
+                .fg(Color::DarkGray)
+                .add_modifier(Modifier::BOLD),
+        )));
+    }
+
+    /// Like [`Container::proc_and_push_line`], showing only the chosen columns of structured lines.
+    pub fn proc_and_push_input(&mut self, line: &InputLine) -> Option<Line<'a>> {
+        if let Some(value) = self.extract_value(line) {
+            if let Some(series) = &mut self.series {
+                series.push(value);
+            }
+        }
+        match &line.fields {
+            Some(fields) if !self.columns.is_empty() => {
+                let text = render_columns(&self.columns, fields);
+                // The pattern may match a field that is not shown
+                let shown = self.process_line(&text).unwrap_or_else(|| Line::from(text));
+                self.push_and_act(Some(shown), &line.text)
+            }
+            _ => self.proc_and_push_line(&line.text),
+        }
+    }
+
+    /// Plots the value of a capture group, or of a field of structured lines.
+    pub fn set_series(&mut self, name: &str) {
+        self.series = Some(Series::new(name, self.cb.capacity()));
+    }
+
+    /// Number held by the capture group or field of the series, durations in seconds.
+    fn extract_value(&self, line: &InputLine) -> Option<f64> {
+        let name = &self.series.as_ref()?.name;
+        if let Some(caps) = self.re.captures(&line.text) {
+            let group = match name.parse::<usize>() {
+                Ok(index) => caps.get(index),
+                Err(_) => caps.name(name),
+            };
+            if let Some(group) = group {
+                return parse_number(group.as_str());
+            }
+        }
+        as_number(lookup(line.fields.as_ref()?, name)?)
     }
 
     pub fn proc_and_push_line(&mut self, line: &str) -> Option<Line<'a>> {
         let processed_line = self.process_line(line);
+        self.push_and_act(processed_line, line)
+    }
+
+    /// Pushes the processed line, writes the original one and runs the trigger.
+    fn push_and_act(&mut self, processed_line: Option<Line<'a>>, line: &str) -> Option<Line<'a>> {
         if let Some(processed_line_clone) = processed_line.clone() {
             self.push(processed_line_clone);
         }
-        if let Some(file) = &mut self.file {
-            file.write_all(line.as_bytes())
-                .expect("Failed to write file");
-            file.flush().expect("Failed to flush");
-        }
-        if self.trigger.is_some() && self.thread_pool.is_some() {
-            let cmd = self.trigger.clone().unwrap().replace("__line__", line);
-            let mut child = Command::new("sh").arg("-c").arg(cmd).spawn().unwrap();
-            let timeout = Duration::from_secs(self.timeout);
-            self.thread_pool.as_ref().unwrap().execute(move || {
-                let _status_code = match child.wait_timeout(timeout).unwrap() {
-                    Some(status) => status.code(),
-                    None => {
-                        // child hasn't exited yet
-                        child.kill().unwrap();
-                        child.wait().unwrap().code()
-                    }
-                };
-            });
+        if let Some(output) = &mut self.output {
+            if let Err(e) = output.write(line, Instant::now()) {
+                self.output_error = Some(format!("{}: {e}", output.path().display()));
+            }
+        }
+        if self.alerts.is_empty() {
+            self.run_trigger(line);
         }
 
         processed_line
     }
 
+    pub fn set_alerts(&mut self, rules: &[AlertRule]) -> Result<()> {
+        let now = Instant::now();
+        self.alerts = rules
+            .iter()
+            .map(|rule| Alert::new(rule, now))
+            .collect::<Result<_, _>>()
+            .map_err(anyhow::Error::msg)?;
+        Ok(())
+    }
+
+    /// Runs the trigger once for each alert that starts firing, with the alert as `__line__`.
+    pub fn check_alerts(&mut self, now: Instant) {
+        let mut fired = vec![];
+        for alert in self.alerts.iter_mut() {
+            if alert.check(now) {
+                fired.push(alert.to_string());
+            }
+        }
+        for alert in fired {
+            self.run_trigger(&alert);
+        }
+    }
+
+    /// First alert that is firing
+    pub fn firing_alert(&self) -> Option<&Alert> {
+        self.alerts.iter().find(|a| a.firing_since().is_some())
+    }
+
+    /// Context of the trigger, the capture groups of the line included.
+    fn trigger_event(&self, line: &str) -> TriggerEvent {
+        let mut event = TriggerEvent::new(line, &self.text);
+        if let Some(caps) = self.re.captures(line) {
+            for (i, name) in self.re.capture_names().enumerate().skip(1) {
+                if let Some(group) = caps.get(i) {
+                    let name = name.map_or_else(|| i.to_string(), str::to_string);
+                    event.captures.push((name, group.as_str().to_string()));
+                }
+            }
+        }
+        event
+    }
+
+    /// Starts the built-in actions, their outcomes go where the ones of the trigger go.
+    pub fn set_actions(&mut self, rules: &[ActionRule]) -> Result<()> {
+        self.actions = rules
+            .iter()
+            .map(|rule| Action::new(rule, self.id, self.results.clone()))
+            .collect::<Result<_, _>>()
+            .map_err(anyhow::Error::msg)?;
+        Ok(())
+    }
+
+    fn run_trigger(&self, line: &str) {
+        if self.trigger.is_none() && self.actions.is_empty() {
+            return;
+        }
+        let event = self.trigger_event(line);
+        for action in self.actions.iter() {
+            action.send(event.clone());
+        }
+        let (Some(trigger), Some(queue)) = (&self.trigger, &self.queue) else {
+            return;
+        };
+        queue.push(TriggerJob {
+            id: self.id,
+            command: trigger.clone(),
+            input: self.trigger_input,
+            timeout: Duration::from_secs(self.timeout),
+            event,
+            results: self.results.clone(),
+        });
+    }
+
     pub fn update_scroll(&mut self, visible_lines: usize, scroll: &ScrollDirection) {
-        let total_lines = self.cb.len();
+        // A locked view stays on its lines while the oldest ones are pushed out
+        let first = self.first_line();
+        if self.state.scroll_lock {
+            let evicted = first.saturating_sub(self.scroll_first);
+            self.state.scroll = self
+                .state
+                .scroll
+                .saturating_sub(evicted.min(u16::MAX as usize) as u16);
+        }
+        self.scroll_first = first;
+        let total_lines = self.shown_len();
 
         // If we have less lines in the buffer than visible lines then do nothing
         if total_lines < visible_lines {
@@ -175,7 +641,7 @@ impl<'a> Container<'a> {
 
         let max_scroll = (total_lines - visible_lines) as u16;
 
-        if !self.state.paused {
+        if !self.state.paused && !self.state.scroll_lock {
             // This ensures automatic scrolling
             self.state.scroll = max_scroll;
         } else {
@@ -195,21 +661,116 @@ impl<'a> Container<'a> {
         }
     }
 
+    /// Scrolls so that the line at `index` is in the middle of the view.
+    pub fn scroll_to(&mut self, index: usize, visible_lines: usize) {
+        let max_scroll = self.shown_len().saturating_sub(visible_lines);
+        self.state.scroll = index.saturating_sub(visible_lines / 2).min(max_scroll) as u16;
+    }
+
+    /// Lines shown, the oldest first
+    pub fn lines(&self) -> Vec<Line<'a>> {
+        self.cb
+            .ordered_clone()
+            .buffer
+            .iter()
+            .flat_map(|entry| unfold(entry, !self.state.unfold))
+            .collect()
+    }
+
+    /// Number of lines shown, a folded event counting as one
+    fn shown_len(&self) -> usize {
+        if !self.state.unfold {
+            return self.cb.len();
+        }
+        self.cb
+            .buffer
+            .iter()
+            .map(|entry| unfold(entry, false).len())
+            .sum()
+    }
+
     pub fn render(&self, frame: &mut Frame, area: Rect) {
         if self.state.hide {
             return;
         }
-        let title = format!("[{}]'{}' ({})", self.id, self.text, self.state.count);
-        let block = create_block(&title, self.state.color, self.state.paused);
-        let mut paragraph = Paragraph::new(self.cb.ordered_clone().buffer.clone())
-            .block(block)
+        let excludes: String = self.exclude.iter().map(|re| format!(" !'{re}'")).collect();
+        let mut title = format!(
+            "[{}]{}{excludes} ({})",
+            self.id,
+            self.description(),
+            self.state.count
+        );
+        let stats = self.series.as_ref().and_then(|s| s.stats());
+        if let Some(stats) = stats {
+            title.push_str(&format!(" {stats}"));
+        }
+        if self.state.excluded > 0 {
+            title.push_str(&format!(" [{} excluded]", self.state.excluded));
+        }
+        if self.state.trigger_failures > 0 {
+            title.push_str(&format!(
+                " [{} failed triggers]",
+                self.state.trigger_failures
+            ));
+        }
+        let alert = self.firing_alert();
+        if let Some(alert) = alert {
+            title.push_str(&format!(" ALERT: {alert}"));
+        }
+        let stopped = self.state.paused || self.state.scroll_lock;
+        let mut block = create_block(&title, self.state.color, stopped);
+        if let Some(since) = alert.and_then(|a| a.firing_since()) {
+            // Flashes twice per second
+            let mut style = Style::default().fg(Color::Red);
+            if since.elapsed().as_millis() / 250 % 2 == 0 {
+                style = style.add_modifier(Modifier::REVERSED);
+            }
+            block = block.border_style(style);
+        }
+        let inner = block.inner(area);
+        let [text, sparkline] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(self.sparkline_height(inner.height)),
+        ])
+        .areas(inner);
+        let mut lines = self.lines();
+        if let Some(search) = &self.search {
+            let first = self.first_line();
+            for (i, line) in lines.iter_mut().enumerate() {
+                *line = search.highlight(line, search.hit == Some(first + i));
+            }
+        }
+        let mut paragraph = Paragraph::new(lines)
             .style(self.state.style)
             .scroll((self.state.scroll, 0));
         if self.state.wrap {
             paragraph = paragraph.wrap(Wrap { trim: false });
         }
 
-        frame.render_widget(paragraph, area);
+        frame.render_widget(block.style(self.state.style), area);
+        frame.render_widget(paragraph, text);
+        if let Some(series) = &self.series {
+            if !sparkline.is_empty() {
+                series.render(frame, sparkline, self.state.color);
+            }
+        }
+    }
+
+    /// Number of lines shown in a container of the given height, between its borders
+    /// and above its sparkline.
+    pub fn visible_lines(&self, height: u16) -> usize {
+        let inner = height.saturating_sub(2);
+        (inner - self.sparkline_height(inner)) as usize
+    }
+
+    /// Rows taken by the sparkline below the lines, if there is room left for them.
+    fn sparkline_height(&self, inner_height: u16) -> u16 {
+        let plotted = self.series.as_ref().is_some_and(|s| s.stats().is_some());
+        if plotted && inner_height > 2 * SPARKLINE_HEIGHT {
+            SPARKLINE_HEIGHT
+        } else {
+            0
+        }
     }
 
     pub fn get_count(&self) -> u64 {
@@ -218,6 +779,11 @@ impl<'a> Container<'a> {
 
     pub fn reset(&mut self) {
         self.cb.reset();
+        self.evicted_lines = 0;
+        self.scroll_first = 0;
+        if let Some(series) = &mut self.series {
+            series.reset();
+        }
     }
 }
 
@@ -236,6 +802,10 @@ fn create_block(title: &str, color: Color, paused: bool) -> Block<'_> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::{
+        output::{OUTPUT_FLUSH, OUTPUT_TEMPLATE},
+        trigger::TriggerStatus,
+    };
 
     #[test]
     fn test_create_block() {
@@ -273,10 +843,249 @@ mod tests {
         let path = std::path::PathBuf::from("test-sarasa");
         let mut dir = std::fs::DirBuilder::new();
         dir.recursive(true).create("test-sarasa").unwrap();
-        assert!(container.set_output_path(path).is_ok());
+        assert!(container
+            .set_output_path(path, OUTPUT_TEMPLATE, &RotateRule::default())
+            .is_ok());
+        container.proc_and_push_line("a key line\n");
+        assert!(container
+            .tick_output(Instant::now() + OUTPUT_FLUSH)
+            .is_none());
+        assert_eq!(
+            std::fs::read_to_string("test-sarasa/key.txt").unwrap(),
+            "a key line\n"
+        );
         let _ = std::fs::remove_dir_all("test-sarasa");
     }
 
+    #[test]
+    fn matches() {
+        let mut container = Container::new("error".to_string(), None, 1, 0, 2);
+        assert!(container.matches(&InputLine::new("api", "an error".to_string())));
+        assert!(!container.matches(&InputLine::new("api", "all good".to_string())));
+
+        container.set_source("^api$").unwrap();
+        assert!(container.matches(&InputLine::new("api", "an error".to_string())));
+        assert!(!container.matches(&InputLine::new("web", "an error".to_string())));
+        assert!(container.set_source("*").is_err());
+    }
+
+    #[test]
+    fn matches_when() {
+        let mut container = Container::new(String::new(), None, 1, 0, 2);
+        container.set_when("http.status >= 500").unwrap();
+        assert_eq!(container.description(), "http.status >= 500");
+        let mut line = InputLine::from("status 503");
+        assert!(!container.matches(&line));
+        line.fields = Some(Box::new(serde_json::json!({"http": {"status": 503}})));
+        assert!(container.matches(&line));
+        line.fields = Some(Box::new(serde_json::json!({"http": {"status": 200}})));
+        assert!(!container.matches(&line));
+
+        let mut container = Container::new("GET".to_string(), None, 1, 0, 2);
+        assert_eq!(container.description(), "'GET'");
+        container.set_when("http.status >= 500").unwrap();
+        assert_eq!(container.description(), "'GET' if http.status >= 500");
+        assert!(container.set_when("http.status").is_err());
+    }
+
+    #[test]
+    fn proc_and_push_input_columns() {
+        let mut container = Container::new("err".to_string(), None, 1, 0, 2);
+        container.columns = vec!["level".to_string(), "took".to_string()];
+        let mut line = InputLine::from("level=error took=2s msg=x\n");
+        line.fields = Some(Box::new(
+            serde_json::json!({"level": "error", "took": "2s", "msg": "x"}),
+        ));
+        container.proc_and_push_input(&line);
+        assert_eq!(
+            container.cb.buffer[0],
+            Line::from(vec![
+                Span::from(""),
+                Span::styled("err", Style::default().fg(Color::Red)),
+                Span::from("or 2s"),
+            ])
+        );
+
+        // The match is not among the columns
+        container.columns = vec!["took".to_string()];
+        container.proc_and_push_input(&line);
+        assert_eq!(container.cb.buffer[1], Line::from("2s"));
+
+        // Raw lines are shown whole
+        container.proc_and_push_input(&InputLine::from("an error"));
+        assert_eq!(container.get_count(), 3);
+    }
+
+    #[test]
+    fn series() {
+        let mut container = Container::new(r"took (?P<took>\S+)".to_string(), None, 1, 0, 4);
+        container.set_series("took");
+        container.proc_and_push_input(&InputLine::from("GET took 250ms"));
+        container.proc_and_push_input(&InputLine::from("GET took 2"));
+        container.proc_and_push_input(&InputLine::from("GET took never"));
+        let mut line = InputLine::from("level=info");
+        line.fields = Some(Box::new(serde_json::json!({"took": "1.5s"})));
+        container.proc_and_push_input(&line);
+        assert_eq!(
+            container.series.as_ref().unwrap().values(),
+            vec![0.25, 2.0, 1.5]
+        );
+
+        container.set_series("1");
+        container.proc_and_push_input(&InputLine::from("took 3"));
+        assert_eq!(
+            container
+                .series
+                .as_ref()
+                .unwrap()
+                .stats()
+                .unwrap()
+                .to_string(),
+            "min=3 max=3 avg=3 p95=3"
+        );
+
+        let backend = ratatui::backend::TestBackend::new(60, 9);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| container.render(f, f.area())).unwrap();
+        let buffer = terminal.backend().buffer();
+        let title: String = (0..60).map(|x| buffer[(x, 0)].symbol()).collect();
+        assert!(title.contains("(4) min=3 max=3 avg=3 p95=3"));
+        // Flat line in the middle of the sparkline
+        assert_eq!(buffer[(1, 5)].symbol(), " ");
+        assert_eq!(buffer[(1, 6)].symbol(), "▄");
+        assert_eq!(buffer[(1, 7)].symbol(), "█");
+    }
+
+    #[test]
+    fn alerts() {
+        let path = std::env::temp_dir().join(format!("logss-alerts-{}", std::process::id()));
+        let mut container = Container::new(
+            "error".to_string(),
+            Some(format!("echo '__line__' >> {}", path.display())),
+            1,
+            1,
+            8,
+        );
+        container
+            .set_alerts(&[AlertRule {
+                above: Some(1),
+                ..Default::default()
+            }])
+            .unwrap();
+        assert!(container.set_alerts(&[AlertRule::default()]).is_err());
+
+        // Not on every match
+        let now = Instant::now();
+        container.proc_and_push_line("an error");
+        container.check_alerts(now);
+        assert!(container.firing_alert().is_none());
+        container.proc_and_push_line("another error");
+        container.proc_and_push_line("and another error");
+        container.check_alerts(now);
+        container.check_alerts(now);
+        assert_eq!(
+            container.firing_alert().unwrap().to_string(),
+            "more than 1 in 60s"
+        );
+        container.queue.as_ref().unwrap().join();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "more than 1 in 60s\n"
+        );
+        std::fs::remove_file(&path).unwrap();
+
+        let backend = ratatui::backend::TestBackend::new(60, 3);
+        let mut terminal = ratatui::Terminal::new(backend).unwrap();
+        terminal.draw(|f| container.render(f, f.area())).unwrap();
+        let buffer = terminal.backend().buffer();
+        let title: String = (0..60).map(|x| buffer[(x, 0)].symbol()).collect();
+        assert!(title.contains("(3) ALERT: more than 1 in 60s"));
+        assert_eq!(buffer[(0, 1)].fg, Color::Red);
+    }
+
+    #[test]
+    fn actions() {
+        let path = std::env::temp_dir().join(format!("logss-actions-{}", std::process::id()));
+        let mut container = Container::new("error".to_string(), None, 1, 0, 2);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        container.id = 4;
+        container.results = Some(sender);
+        container
+            .set_actions(&[ActionRule {
+                file: Some(path.clone()),
+                ..Default::default()
+            }])
+            .unwrap();
+        assert!(container.set_actions(&[ActionRule::default()]).is_err());
+        container
+            .set_actions(&[ActionRule {
+                file: Some(path.clone()),
+                ..Default::default()
+            }])
+            .unwrap();
+
+        container.proc_and_push_line("an error\n");
+        let outcome = receiver.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!(outcome.id, 4);
+        assert_eq!(outcome.status, TriggerStatus::Done);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "an error\n");
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn trigger_event() {
+        let container = Container::new(r"(?P<level>ERROR) (\w+)".to_string(), None, 1, 0, 2);
+        let event = container.trigger_event("ERROR db: $(reboot)\n");
+        assert_eq!(event.line, "ERROR db: $(reboot)");
+        assert_eq!(event.container, r"(?P<level>ERROR) (\w+)");
+        assert_eq!(
+            event.captures,
+            vec![
+                ("level".to_string(), "ERROR".to_string()),
+                ("2".to_string(), "db".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn push_separator() {
+        let mut container = Container::new("key".to_string(), None, 1, 0, 2);
+        container.push_separator("api restarted");
+        assert_eq!(container.get_count(), 0);
+        assert_eq!(
+            container.cb.buffer[0],
+            Line::from(Span::styled(
+                "──── api restarted ────",
+                Style::default()
+                    .fg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD)
+            ))
+        );
+    }
+
+    #[test]
+    fn context_keeps_line_breaks() {
+        let mut container = Container::new("ERROR".to_string(), None, 1, 0, 4);
+        let mut raw = CircularBuffer::new(4);
+        raw.push(Line::from(Span::raw("WARN slow\n  at b\n")));
+        container.push_context(raw.newest(0).unwrap());
+        container.state.unfold = true;
+        let lines: Vec<String> = container.lines().iter().map(line_text).collect();
+        assert_eq!(lines, vec!["WARN slow", "  at b"]);
+        assert_eq!(container.get_count(), 0);
+    }
+
+    #[test]
+    fn restore_keeps_line_breaks() {
+        let mut container = Container::new("ERROR".to_string(), None, 1, 0, 4);
+        container.restore("ERROR boom\n  at a\n");
+        container.restore("INFO ok\n");
+        assert_eq!(container.get_count(), 0);
+        container.state.unfold = true;
+        let lines: Vec<String> = container.lines().iter().map(line_text).collect();
+        assert_eq!(lines, vec!["ERROR boom", "  at a", "INFO ok"]);
+    }
+
     #[test]
     fn process_line() {
         let container = Container::new("stringtomatch".to_string(), None, 1, 0, 2);
@@ -290,4 +1099,90 @@ mod tests {
         ]));
         assert_eq!(span, expected_span);
     }
+
+    #[test]
+    fn expr() {
+        let mut container = Container::new_clean("");
+        container
+            .set_expr(r"(payment or checkout) and not 2\d\d")
+            .unwrap();
+        assert!(container.set_expr("payment and").is_err());
+        assert_eq!(
+            container.description(),
+            r"(payment or checkout) and not 2\d\d"
+        );
+        assert!(container.matches(&InputLine::new("", "POST /payment 502".to_string())));
+        assert!(!container.matches(&InputLine::new("", "POST /payment 200".to_string())));
+        assert!(!container.matches(&InputLine::new("", "POST /cart 502".to_string())));
+        assert_eq!(container.process_line("POST /cart 502"), None);
+        // Only the patterns that are not negated are highlighted
+        let red = Style::default().fg(Color::Red);
+        assert_eq!(
+            container.process_line("POST /checkout 500"),
+            Some(Line::from(vec![
+                Span::from("POST /"),
+                Span::styled("checkout", red),
+                Span::from(" 500"),
+            ]))
+        );
+
+        // Matches without any of its patterns
+        container.set_expr("not ok").unwrap();
+        assert_eq!(
+            container.process_line("fine"),
+            Some(Line::from(vec![Span::from("fine")]))
+        );
+    }
+
+    #[test]
+    fn process_line_all_matches() {
+        let container = Container::new("o+".to_string(), None, 1, 0, 2);
+        let span = container.process_line("foo boo x");
+        let expected_span = Some(Line::from(vec![
+            Span::from("f"),
+            Span::styled("oo", Style::default().fg(Color::Red)),
+            Span::from(" b"),
+            Span::styled("oo", Style::default().fg(Color::Red)),
+            Span::from(" x"),
+        ]));
+        assert_eq!(span, expected_span);
+
+        // Empty matches are not highlighted
+        let container = Container::new("x*".to_string(), None, 1, 0, 2);
+        let span = container.process_line("ab");
+        assert_eq!(span, Some(Line::from(vec![Span::from("ab")])));
+    }
+
+    #[test]
+    fn process_line_capture_colors() {
+        let mut container = Container::new(
+            r"(?P<level>ERROR|WARN) (?P<module>\w+)(?P<rest>.)?".to_string(),
+            None,
+            1,
+            0,
+            2,
+        );
+        let colors = BTreeMap::from([
+            ("level".to_string(), "yellow".to_string()),
+            ("module".to_string(), "#00ff00".to_string()),
+        ]);
+        container.set_colors(&colors).unwrap();
+        let span = container.process_line("1 WARN db: x, ERROR api");
+        let expected_span = Some(Line::from(vec![
+            Span::from("1 "),
+            Span::styled("WARN", Style::default().fg(Color::Yellow)),
+            Span::styled(" ", Style::default().fg(Color::Red)),
+            Span::styled("db", Style::default().fg(Color::Rgb(0, 255, 0))),
+            Span::styled(":", Style::default().fg(Color::Red)),
+            Span::from(" x, "),
+            Span::styled("ERROR", Style::default().fg(Color::Yellow)),
+            Span::styled(" ", Style::default().fg(Color::Red)),
+            Span::styled("api", Style::default().fg(Color::Rgb(0, 255, 0))),
+            Span::from(""),
+        ]));
+        assert_eq!(span, expected_span);
+
+        let colors = BTreeMap::from([("level".to_string(), "not a color".to_string())]);
+        assert!(container.set_colors(&colors).is_err());
+    }
 }
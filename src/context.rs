@@ -0,0 +1,81 @@
+use std::ops::Range;
+
+/// Raw lines shown around the matches of a container, like `grep -B/-A`.
+///
+/// Lines are identified by their position among the raw lines of the source of the
+/// container, so overlapping contexts show each line once.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct Context {
+    /// lines shown before each match
+    pub before: usize,
+    /// lines shown after each match
+    pub after: usize,
+    /// lines after the last match still to show
+    left: usize,
+    /// position of the last line shown
+    last: Option<usize>,
+}
+
+impl Context {
+    pub fn new(before: usize, after: usize) -> Self {
+        Self {
+            before,
+            after,
+            ..Default::default()
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.before > 0 || self.after > 0
+    }
+
+    /// Records a match at `position`, returns the positions of the lines to show
+    /// before it and whether they are apart from the last line shown.
+    pub fn on_match(&mut self, position: usize) -> (Range<usize>, bool) {
+        let first_unseen = self.last.map_or(0, |last| last + 1);
+        let start = position.saturating_sub(self.before).max(first_unseen);
+        let apart = self.is_enabled() && self.last.is_some_and(|last| start > last + 1);
+        self.last = Some(position);
+        self.left = self.after;
+        (start..position, apart)
+    }
+
+    /// Returns true if the line at `position`, which did not match, has to be shown.
+    pub fn on_other(&mut self, position: usize) -> bool {
+        if self.left == 0 {
+            return false;
+        }
+        self.left -= 1;
+        self.last = Some(position);
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context() {
+        let mut context = Context::new(2, 1);
+        assert!(context.is_enabled());
+        assert_eq!(context.on_match(1), (0..1, false));
+        assert!(context.on_other(2));
+        assert!(!context.on_other(3));
+        // Line 4 is before the match, but 3 was not shown
+        assert!(!context.on_other(4));
+        assert_eq!(context.on_match(5), (3..5, false));
+        assert!(context.on_other(6));
+        assert_eq!(context.on_match(10), (8..10, true));
+        // Overlapping contexts
+        assert_eq!(context.on_match(11), (11..11, false));
+        assert!(context.on_other(12));
+        assert_eq!(context.on_match(13), (13..13, false));
+
+        let mut context = Context::default();
+        assert!(!context.is_enabled());
+        assert_eq!(context.on_match(1), (1..1, false));
+        assert!(!context.on_other(2));
+        assert_eq!(context.on_match(5), (5..5, false));
+    }
+}
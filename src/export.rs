@@ -0,0 +1,250 @@
+use std::{
+    fmt,
+    fs::OpenOptions,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::Line,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use crate::search::line_text;
+
+/// How the lines of a buffer are exported.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    /// Plain text, one line per entry
+    #[default]
+    Text,
+    /// One JSON object per entry, `{"line": ...}`
+    Jsonl,
+    /// Text with the colors of the view, as ANSI escape codes
+    Ansi,
+}
+
+impl ExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Text => "txt",
+            Self::Jsonl => "jsonl",
+            Self::Ansi => "ansi",
+        }
+    }
+
+    /// The format after this one, to switch between them
+    pub fn next(&self) -> Self {
+        match self {
+            Self::Text => Self::Jsonl,
+            Self::Jsonl => Self::Ansi,
+            Self::Ansi => Self::Text,
+        }
+    }
+}
+
+impl FromStr for ExportFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Self::Text),
+            "jsonl" => Ok(Self::Jsonl),
+            "ansi" => Ok(Self::Ansi),
+            _ => Err(format!("`{s}` isn't a valid export format")),
+        }
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text => write!(f, "text"),
+            Self::Jsonl => write!(f, "jsonl"),
+            Self::Ansi => write!(f, "ansi"),
+        }
+    }
+}
+
+/// Free path for the export of a view, e.g. `logss-raw-1700000000.txt`,
+/// or `logss-raw-1700000000-<n>.txt` if exported more than once in a second.
+pub fn export_path(dir: &Path, name: &str, format: ExportFormat) -> PathBuf {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs());
+    let extension = format.extension();
+    std::iter::once(dir.join(format!("logss-{name}-{now}.{extension}")))
+        .chain((1..).map(|n| dir.join(format!("logss-{name}-{now}-{n}.{extension}"))))
+        .find(|p| !p.exists())
+        .unwrap()
+}
+
+/// Writes the lines, the oldest first, to a new file, never overwriting one.
+pub fn export(lines: &[Line], format: ExportFormat, path: &Path) -> io::Result<()> {
+    let file = OpenOptions::new().write(true).create_new(true).open(path)?;
+    let mut writer = BufWriter::new(file);
+    for line in lines {
+        let text = match format {
+            ExportFormat::Text => line_text(line),
+            ExportFormat::Jsonl => {
+                json!({ "line": line_text(line).trim_end_matches('\n') }).to_string()
+            }
+            ExportFormat::Ansi => ansi(line),
+        };
+        writeln!(writer, "{}", text.trim_end_matches('\n'))?;
+    }
+    writer.flush()
+}
+
+/// Text of the line with its styles as ANSI escape codes.
+pub fn ansi(line: &Line) -> String {
+    let mut ret = String::new();
+    let last = line.spans.len().saturating_sub(1);
+    for (i, span) in line.spans.iter().enumerate() {
+        // The line break stays out of the styles
+        let content = if i == last {
+            span.content.trim_end_matches('\n')
+        } else {
+            &span.content
+        };
+        let codes = sgr(&line.style.patch(span.style));
+        if codes.is_empty() {
+            ret.push_str(content);
+        } else {
+            ret.push_str(&format!("\x1b[{}m{content}\x1b[0m", codes.join(";")));
+        }
+    }
+    ret
+}
+
+/// Select Graphic Rendition parameters of a style.
+fn sgr(style: &Style) -> Vec<String> {
+    let mut codes = vec![];
+    for (modifier, code) in [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+        (Modifier::CROSSED_OUT, "9"),
+    ] {
+        if style.add_modifier.contains(modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    codes.extend(style.fg.and_then(|c| color(c, false)));
+    codes.extend(style.bg.and_then(|c| color(c, true)));
+    codes
+}
+
+fn color(color: Color, background: bool) -> Option<String> {
+    let base = match color {
+        Color::Reset => return None,
+        Color::Black => 0,
+        Color::Red => 1,
+        Color::Green => 2,
+        Color::Yellow => 3,
+        Color::Blue => 4,
+        Color::Magenta => 5,
+        Color::Cyan => 6,
+        Color::Gray => 7,
+        Color::DarkGray => 60,
+        Color::LightRed => 61,
+        Color::LightGreen => 62,
+        Color::LightYellow => 63,
+        Color::LightBlue => 64,
+        Color::LightMagenta => 65,
+        Color::LightCyan => 66,
+        Color::White => 67,
+        Color::Indexed(i) => return Some(format!("{};5;{i}", if background { 48 } else { 38 })),
+        Color::Rgb(r, g, b) => {
+            return Some(format!(
+                "{};2;{r};{g};{b}",
+                if background { 48 } else { 38 }
+            ))
+        }
+    };
+    Some((base + if background { 40 } else { 30 }).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::text::Span;
+
+    use super::*;
+
+    #[test]
+    fn formats() {
+        let lines = vec![
+            Line::from(vec![
+                Span::raw("GET "),
+                Span::styled("/api", Style::default().fg(Color::Red)),
+                Span::raw(" 500\n"),
+            ]),
+            Line::from(Span::raw("ERROR \"boom\"\n  at a\n")),
+        ];
+        let dir = std::env::temp_dir();
+        let mut exported = vec![];
+        for format in [ExportFormat::Text, ExportFormat::Jsonl, ExportFormat::Ansi] {
+            let path = dir.join(format!("logss-export-{}.{format}", std::process::id()));
+            export(&lines, format, &path).unwrap();
+            exported.push(std::fs::read_to_string(&path).unwrap());
+            std::fs::remove_file(&path).unwrap();
+        }
+        assert_eq!(exported[0], "GET /api 500\nERROR \"boom\"\n  at a\n");
+        assert_eq!(
+            exported[1],
+            "{\"line\":\"GET /api 500\"}\n{\"line\":\"ERROR \\\"boom\\\"\\n  at a\"}\n"
+        );
+        assert_eq!(
+            exported[2],
+            "GET \x1b[31m/api\x1b[0m 500\nERROR \"boom\"\n  at a\n"
+        );
+    }
+
+    #[test]
+    fn ansi_styles() {
+        let style = Style::default()
+            .fg(Color::LightMagenta)
+            .bg(Color::Indexed(17))
+            .add_modifier(Modifier::BOLD);
+        let line = Line::styled("x", style);
+        assert_eq!(ansi(&line), "\x1b[1;95;48;5;17mx\x1b[0m");
+        let line = Line::from(Span::styled(
+            "y\n",
+            Style::default().fg(Color::Rgb(1, 2, 3)),
+        ));
+        assert_eq!(ansi(&line), "\x1b[38;2;1;2;3my\x1b[0m");
+
+        let path = export_path(Path::new("out"), "raw", ExportFormat::Jsonl);
+        let name = path.file_name().unwrap().to_string_lossy().to_string();
+        assert!(name.starts_with("logss-raw-") && name.ends_with(".jsonl"));
+        assert_eq!(ExportFormat::Ansi.next(), ExportFormat::Text);
+        assert_eq!("jsonl".parse(), Ok(ExportFormat::Jsonl));
+        assert!("csv".parse::<ExportFormat>().is_err());
+    }
+
+    #[test]
+    fn unique_paths() {
+        let dir = std::env::temp_dir().join(format!("logss-export-unique-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let lines = vec![Line::from("a")];
+        let mut paths = vec![];
+        for _ in 0..3 {
+            let path = export_path(&dir, "raw", ExportFormat::Text);
+            export(&lines, ExportFormat::Text, &path).unwrap();
+            paths.push(path);
+        }
+        paths.dedup();
+        assert_eq!(paths.len(), 3);
+        assert!(export(&lines, ExportFormat::Text, &paths[0]).is_err());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 3);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,296 @@
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+
+/// Boolean combination of regexes, e.g. `(payment or checkout) and 5\d\d and not retry`.
+///
+/// `not` binds tighter than `and`, which binds tighter than `or`. Consecutive words
+/// form a single pattern (`connection refused`), quotes keep keywords and
+/// parentheses as part of it (`"not found"`).
+#[derive(Debug, Clone)]
+pub struct Expr {
+    /// as written
+    expression: String,
+    node: Node,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Pattern(Regex),
+    Not(Box<Node>),
+    And(Box<Node>, Box<Node>),
+    Or(Box<Node>, Box<Node>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    Word(String),
+}
+
+impl Node {
+    fn is_match(&self, text: &str) -> bool {
+        match self {
+            Self::Pattern(re) => re.is_match(text),
+            Self::Not(node) => !node.is_match(text),
+            Self::And(a, b) => a.is_match(text) && b.is_match(text),
+            Self::Or(a, b) => a.is_match(text) || b.is_match(text),
+        }
+    }
+
+    fn patterns<'a>(&'a self, negated: bool, ret: &mut Vec<&'a Regex>) {
+        match self {
+            Self::Pattern(re) if !negated => ret.push(re),
+            Self::Pattern(_) => (),
+            Self::Not(node) => node.patterns(!negated, ret),
+            Self::And(a, b) | Self::Or(a, b) => {
+                a.patterns(negated, ret);
+                b.patterns(negated, ret);
+            }
+        }
+    }
+}
+
+impl Expr {
+    /// Returns true if the text satisfies the expression
+    pub fn is_match(&self, text: &str) -> bool {
+        self.node.is_match(text)
+    }
+
+    /// Patterns whose matches are shown, the negated ones left out
+    pub fn patterns(&self) -> Vec<&Regex> {
+        let mut ret = vec![];
+        self.node.patterns(false, &mut ret);
+        ret
+    }
+
+    /// Regex matching any of the shown patterns, to highlight them.
+    ///
+    /// Falls back to the first one if they can not be combined, e.g. if they
+    /// share capture group names.
+    pub fn highlight(&self) -> Regex {
+        let patterns = self.patterns();
+        let any: Vec<String> = patterns
+            .iter()
+            .map(|re| format!("(?:{})", re.as_str()))
+            .collect();
+        Regex::new(&any.join("|"))
+            .ok()
+            .or_else(|| patterns.first().map(|re| (*re).clone()))
+            .unwrap_or_else(|| Regex::new("").unwrap())
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let tokens = tokenize(s)?;
+        let mut parser = Parser { tokens, pos: 0 };
+        let node = parser.or()?;
+        if let Some(token) = parser.tokens.get(parser.pos) {
+            return Err(format!("unexpected {} in `{s}`", describe(token)));
+        }
+        Ok(Self {
+            expression: s.trim().to_string(),
+            node,
+        })
+    }
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => "`(`".to_string(),
+        Token::Close => "`)`".to_string(),
+        Token::And => "`and`".to_string(),
+        Token::Or => "`or`".to_string(),
+        Token::Not => "`not`".to_string(),
+        Token::Word(w) => format!("`{w}`"),
+    }
+}
+
+/// Splits the expression into words, keywords and the parentheses that group them.
+///
+/// Parentheses belong to a word as long as they are balanced within it, so `f(x)`
+/// stays a pattern and `(payment` opens a group.
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let depth = |w: &str| w.matches('(').count() as i64 - w.matches(')').count() as i64;
+    let mut tokens = vec![];
+    let mut rest = s.trim_start();
+    while !rest.is_empty() {
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        if rest.starts_with('(') && depth(&rest[..end]) > 0 {
+            tokens.push(Token::Open);
+            rest = &rest[1..];
+            continue;
+        }
+        if let Some(quote @ ('"' | '\'')) = rest.chars().next() {
+            let end = rest[1..]
+                .find(quote)
+                .ok_or_else(|| format!("unterminated quote in `{s}`"))?;
+            tokens.push(Token::Word(rest[1..end + 1].to_string()));
+            rest = rest[end + 2..].trim_start();
+            continue;
+        }
+        let mut word = &rest[..end];
+        rest = rest[end..].trim_start();
+        let mut closing = 0;
+        while word.ends_with(')') && depth(word) < 0 {
+            closing += 1;
+            word = &word[..word.len() - 1];
+        }
+        match word {
+            "" => (),
+            "and" => tokens.push(Token::And),
+            "or" => tokens.push(Token::Or),
+            "not" => tokens.push(Token::Not),
+            _ => tokens.push(Token::Word(word.to_string())),
+        }
+        tokens.extend(std::iter::repeat(Token::Close).take(closing));
+    }
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn next_is(&self, token: &Token) -> bool {
+        self.tokens.get(self.pos) == Some(token)
+    }
+
+    fn or(&mut self) -> Result<Node, String> {
+        let mut node = self.and()?;
+        while self.next_is(&Token::Or) {
+            self.pos += 1;
+            node = Node::Or(Box::new(node), Box::new(self.and()?));
+        }
+        Ok(node)
+    }
+
+    fn and(&mut self) -> Result<Node, String> {
+        let mut node = self.unary()?;
+        while self.next_is(&Token::And) {
+            self.pos += 1;
+            node = Node::And(Box::new(node), Box::new(self.unary()?));
+        }
+        Ok(node)
+    }
+
+    fn unary(&mut self) -> Result<Node, String> {
+        match self.tokens.get(self.pos) {
+            Some(Token::Not) => {
+                self.pos += 1;
+                Ok(Node::Not(Box::new(self.unary()?)))
+            }
+            Some(Token::Open) => {
+                self.pos += 1;
+                let node = self.or()?;
+                if !self.next_is(&Token::Close) {
+                    return Err("missing `)`".to_string());
+                }
+                self.pos += 1;
+                Ok(node)
+            }
+            Some(Token::Word(_)) => {
+                // Consecutive words are a single pattern
+                let mut words = vec![];
+                while let Some(Token::Word(word)) = self.tokens.get(self.pos) {
+                    words.push(word.as_str());
+                    self.pos += 1;
+                }
+                let pattern = words.join(" ");
+                Ok(Node::Pattern(
+                    Regex::new(&pattern).map_err(|e| e.to_string())?,
+                ))
+            }
+            Some(token) => Err(format!("unexpected {}", describe(token))),
+            None => Err("missing pattern".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matches(expr: &str, text: &str) -> bool {
+        expr.parse::<Expr>().unwrap().is_match(text)
+    }
+
+    #[test]
+    fn eval() {
+        let expr = "ERROR and not timeout";
+        assert!(matches(expr, "ERROR db down"));
+        assert!(!matches(expr, "ERROR db timeout"));
+        assert!(!matches(expr, "INFO db down"));
+
+        let expr = r"(payment or checkout) and 5\d\d";
+        assert!(matches(expr, "POST /payment 502"));
+        assert!(matches(expr, "POST /checkout 500"));
+        assert!(!matches(expr, "POST /payment 200"));
+        assert!(!matches(expr, "POST /cart 500"));
+
+        // `and` binds tighter than `or`
+        assert!(matches("a or b and c", "a"));
+        assert!(!matches("(a or b) and c", "a"));
+        assert!(matches("not not a", "a"));
+        assert!(matches(
+            "connection refused or not x",
+            "connection refused x"
+        ));
+        assert!(!matches("connection refused", "connection  refused"));
+    }
+
+    #[test]
+    fn parentheses_and_quotes() {
+        // Balanced parentheses belong to the regex
+        assert!(matches(r"f\(x\) and (a|b)c", "f(x) bc"));
+        assert!(matches("((a|b)c or d)", "ac"));
+        assert!(matches("(GET(/api)?)", "GET"));
+        assert!(matches("\"not found\" or 'and'", "page not found"));
+        assert!(matches("\"not found\" or 'and'", "this and that"));
+        assert!(!matches("\"not found\" or 'and'", "not"));
+        assert!(matches("(\"a b\" or c) and d", "a b d"));
+        assert!(matches("(x or \"a b\")", "a b"));
+    }
+
+    #[test]
+    fn errors() {
+        for expr in [
+            "", "a and", "and a", "(a or b", "a or b)", "not", "'a", "a and [",
+        ] {
+            assert!(expr.parse::<Expr>().is_err(), "{expr}");
+        }
+    }
+
+    #[test]
+    fn patterns() {
+        let expr: Expr = "(payment or checkout) and not (retry or timeout)"
+            .parse()
+            .unwrap();
+        let patterns: Vec<&str> = expr.patterns().iter().map(|re| re.as_str()).collect();
+        assert_eq!(patterns, vec!["payment", "checkout"]);
+        assert_eq!(expr.highlight().as_str(), "(?:payment)|(?:checkout)");
+        assert_eq!(
+            expr.to_string(),
+            "(payment or checkout) and not (retry or timeout)"
+        );
+        let expr: Expr = "(?P<a>x) and (?P<a>y)".parse().unwrap();
+        assert_eq!(expr.highlight().as_str(), "(?P<a>x)");
+        let expr: Expr = "not x".parse().unwrap();
+        assert_eq!(expr.highlight().as_str(), "");
+    }
+}
@@ -0,0 +1,518 @@
+use std::{fmt, str::FromStr};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::tstdin::InputLine;
+
+/// How the lines of a source are parsed.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Format {
+    #[default]
+    Raw,
+    Json,
+    Logfmt,
+}
+
+impl FromStr for Format {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "raw" => Ok(Self::Raw),
+            "json" => Ok(Self::Json),
+            "logfmt" => Ok(Self::Logfmt),
+            _ => Err(format!("`{s}` isn't a valid format")),
+        }
+    }
+}
+
+/// Parses structured lines into fields and renders them back as text.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct LineParser {
+    format: Format,
+    /// `{path}` placeholders are replaced by the value of the field
+    template: Option<String>,
+}
+
+impl LineParser {
+    pub fn new(format: Format, template: Option<String>) -> Self {
+        Self { format, template }
+    }
+
+    /// Sets the fields of the line and replaces its text with their rendering.
+    ///
+    /// Lines that can not be parsed are left untouched.
+    pub fn parse(&self, line: &mut InputLine) {
+        let fields = match self.format {
+            Format::Raw => return,
+            Format::Json => serde_json::from_str::<Value>(&line.text).ok(),
+            Format::Logfmt => parse_logfmt(&line.text),
+        };
+        let Some(fields @ Value::Object(_)) = fields else {
+            return;
+        };
+        let newline = if line.text.ends_with('\n') { "\n" } else { "" };
+        let text = match &self.template {
+            Some(template) => render_template(template, &fields),
+            None => render_compact(&fields),
+        };
+        line.text = text + newline;
+        line.fields = Some(Box::new(fields));
+    }
+}
+
+/// Parses a logfmt line, e.g. `level=info msg="it works" took=12ms`.
+///
+/// Values are kept as strings, keys without a value are empty. Lines without
+/// any `key=value` pair are not logfmt.
+pub fn parse_logfmt(line: &str) -> Option<Value> {
+    let mut fields = serde_json::Map::new();
+    let mut pairs = 0;
+    let mut chars = line.trim().chars().peekable();
+    loop {
+        while chars.next_if(|c| c.is_whitespace()).is_some() {}
+        let mut key = String::new();
+        while let Some(c) = chars.next_if(|c| !c.is_whitespace() && *c != '=') {
+            key.push(c);
+        }
+        if key.is_empty() {
+            match chars.peek() {
+                // `=value` without a key
+                Some(_) => return None,
+                None => break,
+            }
+        }
+        let mut value = String::new();
+        if chars.next_if_eq(&'=').is_some() {
+            pairs += 1;
+            if chars.next_if_eq(&'"').is_some() {
+                loop {
+                    match chars.next()? {
+                        '"' => break,
+                        '\\' => match chars.next()? {
+                            'n' => value.push('\n'),
+                            't' => value.push('\t'),
+                            c => value.push(c),
+                        },
+                        c => value.push(c),
+                    }
+                }
+            } else {
+                while let Some(c) = chars.next_if(|c| !c.is_whitespace()) {
+                    value.push(c);
+                }
+            }
+        }
+        fields.insert(key, Value::String(value));
+    }
+    (pairs > 0).then_some(Value::Object(fields))
+}
+
+/// Renders the chosen fields separated by spaces, missing ones as `-`.
+pub fn render_columns(columns: &[String], fields: &Value) -> String {
+    columns
+        .iter()
+        .map(|path| lookup(fields, path).map_or_else(|| "-".to_string(), value_to_string))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// Renders the fields as `key=value` pairs, nested keys are joined with dots.
+pub fn render_compact(fields: &Value) -> String {
+    let mut pairs = vec![];
+    flatten("", fields, &mut pairs);
+    pairs
+        .iter()
+        .map(|(key, value)| format!("{key}={}", quote(value)))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn flatten(prefix: &str, value: &Value, pairs: &mut Vec<(String, String)>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{prefix}.{key}")
+                };
+                flatten(&key, value, pairs);
+            }
+        }
+        _ => pairs.push((prefix.to_string(), value_to_string(value))),
+    }
+}
+
+/// Quotes values that would otherwise be ambiguous.
+fn quote(value: &str) -> String {
+    if value.is_empty() || value.contains(|c: char| c.is_whitespace() || c == '"' || c == '=') {
+        Value::String(value.to_string()).to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+/// Replaces each `{path}` of the template by the value of the field, missing ones are empty.
+pub fn render_template(template: &str, fields: &Value) -> String {
+    let mut ret = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else {
+            break;
+        };
+        ret.push_str(&rest[..start]);
+        let path = &rest[start + 1..start + len];
+        if let Some(value) = lookup(fields, path) {
+            ret.push_str(&value_to_string(value));
+        }
+        rest = &rest[start + len + 1..];
+    }
+    ret.push_str(rest);
+    ret
+}
+
+/// Finds a field by its dotted path, e.g. `http.status` or `items.0`.
+pub fn lookup<'v>(fields: &'v Value, path: &str) -> Option<&'v Value> {
+    // Keys with dots in them take precedence
+    if let Some(value) = fields.get(path) {
+        return Some(value);
+    }
+    path.split('.').try_fold(fields, |value, key| match value {
+        Value::Array(array) => array.get(key.parse::<usize>().ok()?),
+        _ => value.get(key),
+    })
+}
+
+/// Text of a value, strings without their quotes.
+pub fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        _ => value.to_string(),
+    }
+}
+
+/// Numeric value of a field, numbers and durations written as strings included.
+pub fn as_number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => n.as_f64(),
+        Value::String(s) => parse_number(s),
+        _ => None,
+    }
+}
+
+/// A number, or a duration (`1.5s`, `250ms`, `1h30m`) in seconds.
+pub fn parse_number(s: &str) -> Option<f64> {
+    let s = s.trim();
+    s.parse().ok().or_else(|| parse_duration(s))
+}
+
+/// Parses Go style durations into seconds.
+fn parse_duration(s: &str) -> Option<f64> {
+    let is_number = |c: char| c.is_ascii_digit() || c == '.';
+    let mut rest = s;
+    let mut total = 0.0;
+    while !rest.is_empty() {
+        let number_len = rest.find(|c| !is_number(c)).unwrap_or(rest.len());
+        let number: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest.find(is_number).unwrap_or(rest.len());
+        let unit = match &rest[..unit_len] {
+            "ns" => 1e-9,
+            "us" | "µs" => 1e-6,
+            "ms" => 1e-3,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            _ => return None,
+        };
+        total += number * unit;
+        rest = &rest[unit_len..];
+    }
+    (!s.is_empty()).then_some(total)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Eq,
+    Ne,
+    Ge,
+    Le,
+    Gt,
+    Lt,
+    Match,
+}
+
+/// Longest operators first so that `>=` is not taken for `>`.
+const OPS: [(&str, Op); 7] = [
+    ("==", Op::Eq),
+    ("!=", Op::Ne),
+    (">=", Op::Ge),
+    ("<=", Op::Le),
+    ("=~", Op::Match),
+    (">", Op::Gt),
+    ("<", Op::Lt),
+];
+
+#[derive(Debug, Clone)]
+enum Literal {
+    Number(f64),
+    Text(String),
+    Bool(bool),
+    Null,
+    Regex(Regex),
+}
+
+/// Comparison of a field against a value, e.g. `level == "error"` or `http.status >= 500`.
+///
+/// `=~` matches the field against a regex and durations (`took > 1.5s`) are
+/// compared in seconds. Lines without the field never match.
+#[derive(Debug, Clone)]
+pub struct Predicate {
+    expression: String,
+    path: String,
+    op: Op,
+    value: Literal,
+}
+
+impl FromStr for Predicate {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (position, op_str, op) = OPS
+            .iter()
+            .filter_map(|(op_str, op)| s.find(op_str).map(|p| (p, *op_str, *op)))
+            // The leftmost operator, the longest on a tie
+            .min_by_key(|(p, op_str, _)| (*p, usize::MAX - op_str.len()))
+            .ok_or_else(|| format!("no comparison operator in `{s}`"))?;
+        let path = s[..position].trim();
+        if path.is_empty() || path.contains(char::is_whitespace) {
+            return Err(format!("`{path}` isn't a valid field"));
+        }
+        let value = s[position + op_str.len()..].trim();
+        let value = if op == Op::Match {
+            let re = unquote(value).unwrap_or_else(|| value.to_string());
+            Literal::Regex(Regex::new(&re).map_err(|e| e.to_string())?)
+        } else if let Some(text) = unquote(value) {
+            Literal::Text(text)
+        } else {
+            match value {
+                "" => return Err(format!("missing value in `{s}`")),
+                "true" => Literal::Bool(true),
+                "false" => Literal::Bool(false),
+                "null" => Literal::Null,
+                _ => parse_number(value)
+                    .map_or_else(|| Literal::Text(value.to_string()), Literal::Number),
+            }
+        };
+
+        Ok(Self {
+            expression: s.trim().to_string(),
+            path: path.to_string(),
+            op,
+            value,
+        })
+    }
+}
+
+/// Contents of a double quoted string, `None` if it is not quoted.
+fn unquote(s: &str) -> Option<String> {
+    if s.len() < 2 || !s.starts_with('"') || !s.ends_with('"') {
+        return None;
+    }
+    serde_json::from_str(s).ok()
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expression)
+    }
+}
+
+impl Predicate {
+    /// Returns true if the fields satisfy the predicate
+    pub fn eval(&self, fields: &Value) -> bool {
+        let Some(field) = lookup(fields, &self.path) else {
+            return false;
+        };
+        match self.op {
+            Op::Eq => self.equals(field),
+            Op::Ne => !self.equals(field),
+            Op::Match => match &self.value {
+                Literal::Regex(re) => re.is_match(&value_to_string(field)),
+                _ => false,
+            },
+            Op::Ge | Op::Le | Op::Gt | Op::Lt => {
+                let (Some(a), Literal::Number(b)) = (as_number(field), &self.value) else {
+                    return false;
+                };
+                match self.op {
+                    Op::Ge => a >= *b,
+                    Op::Le => a <= *b,
+                    Op::Gt => a > *b,
+                    _ => a < *b,
+                }
+            }
+        }
+    }
+
+    fn equals(&self, field: &Value) -> bool {
+        match &self.value {
+            Literal::Number(n) => as_number(field) == Some(*n),
+            Literal::Text(text) => value_to_string(field) == *text,
+            Literal::Bool(b) => field.as_bool() == Some(*b),
+            Literal::Null => field.is_null(),
+            Literal::Regex(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn parse_json() {
+        let parser = LineParser::new(Format::Json, None);
+        let mut line = InputLine::from(
+            r#"{"level":"error","msg":"it broke","http":{"status":500,"ok":false}}"#.to_string()
+                + "\n",
+        );
+        parser.parse(&mut line);
+        assert_eq!(
+            line.text,
+            "level=error msg=\"it broke\" http.status=500 http.ok=false\n"
+        );
+        assert_eq!(line.fields.unwrap()["http"]["status"], 500);
+
+        // Not JSON
+        let mut line = InputLine::from("plain text");
+        parser.parse(&mut line);
+        assert_eq!(line.text, "plain text");
+        assert_eq!(line.fields, None);
+
+        // Raw is left as is
+        let mut line = InputLine::from(r#"{"a":1}"#);
+        LineParser::default().parse(&mut line);
+        assert_eq!(line.text, r#"{"a":1}"#);
+        assert_eq!(line.fields, None);
+    }
+
+    #[test]
+    fn parse_json_template() {
+        let parser = LineParser::new(Format::Json, Some("[{level}] {msg} {missing}-".to_string()));
+        let mut line = InputLine::from(r#"{"level":"warn","msg":"slow"}"#);
+        parser.parse(&mut line);
+        assert_eq!(line.text, "[warn] slow -");
+    }
+
+    #[test]
+    fn parse_logfmt_lines() {
+        assert_eq!(
+            parse_logfmt(r#"level=info msg="it \"works\"" took=12ms cached http.status=200"#),
+            Some(json!({
+                "level": "info",
+                "msg": "it \"works\"",
+                "took": "12ms",
+                "cached": "",
+                "http.status": "200",
+            }))
+        );
+        assert_eq!(parse_logfmt("a= b=1"), Some(json!({"a": "", "b": "1"})));
+        assert_eq!(parse_logfmt("plain text"), None);
+        assert_eq!(parse_logfmt("=1"), None);
+        assert_eq!(parse_logfmt(r#"msg="unterminated"#), None);
+        assert_eq!(parse_logfmt(""), None);
+
+        let parser = LineParser::new(Format::Logfmt, Some("{level}: {msg}".to_string()));
+        let mut line = InputLine::from("level=warn msg=slow\n");
+        parser.parse(&mut line);
+        assert_eq!(line.text, "warn: slow\n");
+    }
+
+    #[test]
+    fn columns() {
+        let fields = json!({"level": "error", "http": {"status": 500}});
+        let columns = [
+            "http.status".to_string(),
+            "level".to_string(),
+            "x".to_string(),
+        ];
+        assert_eq!(render_columns(&columns, &fields), "500 error -");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_number("12"), Some(12.0));
+        assert_eq!(parse_number("1.5s"), Some(1.5));
+        assert_eq!(parse_number("250ms"), Some(0.25));
+        assert_eq!(parse_number("1h30m"), Some(5400.0));
+        assert_eq!(parse_number("2us"), Some(2e-6));
+        assert_eq!(parse_number("1d"), None);
+        assert_eq!(parse_number("ms"), None);
+        assert_eq!(parse_number(""), None);
+
+        let fields = json!({"took": "1.2s", "fast": "800ms", "status": "503"});
+        let eval = |s: &str| s.parse::<Predicate>().unwrap().eval(&fields);
+        assert!(eval("took > 1s"));
+        assert!(!eval("fast > 1s"));
+        assert!(eval("fast >= 800ms"));
+        assert!(eval("status =~ ^5..$"));
+    }
+
+    #[test]
+    fn lookup_paths() {
+        let fields = json!({"a": {"b": [1, {"c": "d"}]}, "x.y": 2});
+        assert_eq!(lookup(&fields, "a.b.0"), Some(&json!(1)));
+        assert_eq!(lookup(&fields, "a.b.1.c"), Some(&json!("d")));
+        assert_eq!(lookup(&fields, "x.y"), Some(&json!(2)));
+        assert_eq!(lookup(&fields, "a.z"), None);
+    }
+
+    #[test]
+    fn predicates() {
+        let fields = json!({
+            "level": "error",
+            "http": {"status": 503, "code": "404"},
+            "ok": false,
+            "user": null,
+        });
+        let eval = |s: &str| s.parse::<Predicate>().unwrap().eval(&fields);
+        assert!(eval(r#"level == "error""#));
+        assert!(eval("level == error"));
+        assert!(!eval(r#"level != "error""#));
+        assert!(eval("http.status >= 500"));
+        assert!(eval("http.status>500"));
+        assert!(!eval("http.status < 500"));
+        assert!(eval("http.status <= 503"));
+        assert!(eval("http.code == 404"));
+        assert!(eval("http.code > 400"));
+        assert!(eval("ok == false"));
+        assert!(eval("user == null"));
+        assert!(eval("level =~ ^err"));
+        assert!(eval(r#"level =~ "r{2}""#));
+        assert!(!eval("missing == 1"));
+        assert!(!eval("level > 1"));
+    }
+
+    #[test]
+    fn predicate_errors() {
+        assert!("level".parse::<Predicate>().is_err());
+        assert!("== 1".parse::<Predicate>().is_err());
+        assert!("a b == 1".parse::<Predicate>().is_err());
+        assert!("level ==".parse::<Predicate>().is_err());
+        assert!("level =~ (".parse::<Predicate>().is_err());
+        assert_eq!(
+            " level == \"error\" "
+                .parse::<Predicate>()
+                .unwrap()
+                .to_string(),
+            "level == \"error\""
+        );
+    }
+}
@@ -7,22 +7,35 @@ use crate::app::App;
 pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> Result<()> {
     if app.show_input() {
         app.update_input(key_event.code);
+    } else if app.searching() {
+        app.update_search(key_event.code);
     } else {
         match key_event.code {
             // exit application on ESC
             KeyCode::Esc => app.stop(),
             // exit application on Ctrl-D
-            KeyCode::Char('d') | KeyCode::Char('D') => {
-                if key_event.modifiers == KeyModifiers::CONTROL {
-                    app.stop();
-                }
+            KeyCode::Char('d') | KeyCode::Char('D')
+                if key_event.modifiers == KeyModifiers::CONTROL =>
+            {
+                app.stop();
             }
             KeyCode::Char('*') => app.flip_raw_view(),
             KeyCode::Char('s') => app.flip_single_view(),
-            KeyCode::Char('i') | KeyCode::Char('/') => app.flip_show_input(),
+            KeyCode::Char('i') => app.flip_show_input(),
+            KeyCode::Char('/') => app.start_search(),
+            KeyCode::Char('n') => app.next_hit(true),
+            KeyCode::Char('N') => app.next_hit(false),
             KeyCode::Char('h') => app.flip_help(),
             KeyCode::Char('b') => app.flip_barchart(),
+            KeyCode::Char('r') => app.flip_rates(),
+            KeyCode::Char('R') => app.next_rate_bucket(),
+            KeyCode::Char('t') => app.flip_triggers(),
             KeyCode::Char('w') => app.flip_wrap(),
+            KeyCode::Char('f') => app.flip_unfold(),
+            KeyCode::Char('>') => app.step_replay(),
+            KeyCode::Char('S') => app.save_session(),
+            KeyCode::Char('e') => app.export(),
+            KeyCode::Char('E') => app.next_export_format(),
             KeyCode::Char('p') | KeyCode::Char(' ') => app.flip_pause(),
             KeyCode::Char('v') => app.flip_direction(),
             KeyCode::Char('1') => view_helper(app, 1, key_event),
@@ -43,18 +56,11 @@ pub fn handle_key_events(key_event: KeyEvent, app: &mut App) -> Result<()> {
             KeyCode::F(8) => app.hide_view(8),
             KeyCode::Char('9') => view_helper(app, 9, key_event),
             KeyCode::F(9) => app.hide_view(9),
-            KeyCode::Up => {
-                if key_event.kind == KeyEventKind::Press {
-                    app.scroll_up();
-                }
-            }
-            KeyCode::Down => {
-                if key_event.kind == KeyEventKind::Press {
-                    app.scroll_down();
-                }
-            }
+            KeyCode::Up if key_event.kind == KeyEventKind::Press => app.scroll_up(),
+            KeyCode::Down if key_event.kind == KeyEventKind::Press => app.scroll_down(),
             KeyCode::Char('c') => {
                 app.unpause();
+                app.follow();
                 if key_event.modifiers == KeyModifiers::CONTROL {
                     app.stop();
                 }
@@ -155,6 +161,53 @@ mod tests {
         assert!(!app.state.show_input);
     }
 
+    #[test]
+    fn search() {
+        let mut app = App::default();
+        app.add_container("a");
+        app.flip_raw_view();
+        app.state.running = true;
+        for line in ["a 1", "b", "a 2", "b"] {
+            app.raw_buffer.proc_and_push_line(line);
+        }
+        let key = KeyEvent::new(KeyCode::Char('/'), KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        assert!(app.state.searching);
+        assert!(!app.state.show_input);
+        // Typed into the search, not handled as keys
+        for c in ['a', ' ', 'p'] {
+            let key = KeyEvent::new(KeyCode::Char(c), KeyModifiers::NONE);
+            handle_key_events(key, &mut app).ok();
+        }
+        assert_eq!(app.search.hit, None);
+        let key = KeyEvent::new(KeyCode::Backspace, KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        assert_eq!(app.search.query, "a ");
+        assert_eq!(app.search.hit, Some(2));
+        assert!(app.raw_buffer.state.scroll_lock);
+        assert!(!app.state.paused);
+        let key = KeyEvent::new(KeyCode::Enter, KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        assert!(!app.state.searching);
+
+        let key = KeyEvent::new(KeyCode::Char('n'), KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        assert_eq!(app.search.hit, Some(0));
+        let key = KeyEvent::new(KeyCode::Char('N'), KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        assert_eq!(app.search.hit, Some(2));
+
+        let key = KeyEvent::new(KeyCode::Char('/'), KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        let key = KeyEvent::new(KeyCode::Esc, KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        assert!(!app.state.searching);
+        assert!(!app.search.is_active());
+        assert!(!app.raw_buffer.state.scroll_lock);
+        // Esc only closed the search
+        assert!(app.is_running());
+    }
+
     #[test]
     fn flip_help() {
         let mut app = App::default();
@@ -167,6 +220,21 @@ mod tests {
         assert!(!app.state.help);
     }
 
+    #[test]
+    fn flip_rates() {
+        let mut app = App::default();
+        assert!(!app.state.rates);
+        let key = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        assert!(app.state.rates);
+        let key = KeyEvent::new(KeyCode::Char('R'), KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        assert_eq!(app.state.rate_bucket, 1);
+        let key = KeyEvent::new(KeyCode::Char('r'), KeyModifiers::NONE);
+        handle_key_events(key, &mut app).ok();
+        assert!(!app.state.rates);
+    }
+
     #[test]
     fn flip_wrap() {
         let mut app = App::default();
@@ -16,12 +16,56 @@ pub fn render_help(frame: &mut Frame) {
             "b       - toggles BarChart popup",
             Style::default(),
         )),
+        Line::from(Span::styled(
+            "r       - toggles match rate popup",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            "R       - cycles rate buckets (1s, 10s, 1m)",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            "t       - toggles trigger history popup",
+            Style::default(),
+        )),
         Line::from(Span::styled(
             "w       - toggles text wrapping",
             Style::default(),
         )),
         Line::from(Span::styled(
-            "i|/     - input new container (Enter/Esc)",
+            "f       - folds/unfolds multi-line events",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            ">       - replays the next line when stepping",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            "S       - saves the session, resume it with -f",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            "e       - exports the buffer of the view to a file",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            "E       - cycles export formats (text, jsonl, ansi)",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            "i       - input new container (Enter/Esc)",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            "i !re   - excludes lines from zoomed or every view",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            "/       - searches raw, single or zoomed view",
+            Style::default(),
+        )),
+        Line::from(Span::styled(
+            "n/N     - jumps to next/previous search hit",
             Style::default(),
         )),
         Line::from(Span::styled(
@@ -9,6 +9,12 @@ use unicode_width::UnicodeWidthStr;
 
 use crate::popup::{centered_rect, render_popup};
 
+/// Prefix of the input that adds an exclude pattern instead of a container.
+pub const EXCLUDE_PREFIX: char = '!';
+
+/// Escaped [`EXCLUDE_PREFIX`], for containers matching a literal leading `!`.
+const ESCAPED_PREFIX: &str = "\\!";
+
 #[derive(Debug, Default)]
 pub struct Input {
     /// Current value of the input box
@@ -28,10 +34,10 @@ impl Input {
             Style::default(),
         ))];
 
-        let title = if self.is_valid() {
-            "Input"
-        } else {
-            "Input (non valid regexp)"
+        let title = match (self.is_exclude(), self.is_valid()) {
+            (false, true) => "Input",
+            (true, true) => "Input (exclude)",
+            (_, false) => "Input (non valid regexp)",
         };
         let position = Position::new(area.x + self.input.width() as u16 + 1, area.y + 1);
         frame.set_cursor_position(position);
@@ -55,7 +61,26 @@ impl Input {
     }
 
     pub fn is_valid(&self) -> bool {
-        Regex::new(&self.input).is_ok()
+        let pattern = self.exclude_pattern().unwrap_or_else(|| self.pattern());
+        Regex::new(&pattern).is_ok()
+    }
+
+    /// The input starts with [`EXCLUDE_PREFIX`]
+    pub fn is_exclude(&self) -> bool {
+        self.input.starts_with(EXCLUDE_PREFIX)
+    }
+
+    /// Pattern to exclude, `None` if the input is a container
+    pub fn exclude_pattern(&self) -> Option<String> {
+        self.input.strip_prefix(EXCLUDE_PREFIX).map(str::to_string)
+    }
+
+    /// Container pattern, with a leading `\!` unescaped to `!`
+    pub fn pattern(&self) -> String {
+        match self.input.strip_prefix(ESCAPED_PREFIX) {
+            Some(rest) => format!("{EXCLUDE_PREFIX}{rest}"),
+            None => self.input.clone(),
+        }
     }
 }
 
@@ -80,6 +105,41 @@ mod tests {
         assert_eq!(input.input, String::new());
     }
 
+    #[test]
+    fn exclude() {
+        let mut input = Input::new();
+        input.push('(');
+        assert!(!input.is_exclude());
+        assert!(!input.is_valid());
+        input.reset();
+        input.push(EXCLUDE_PREFIX);
+        assert!(input.is_exclude());
+        assert!(input.is_valid());
+        assert_eq!(input.exclude_pattern(), Some(String::new()));
+        input.push('(');
+        assert!(!input.is_valid());
+        input.pop();
+        input.push('a');
+        assert_eq!(input.exclude_pattern(), Some("a".to_string()));
+    }
+
+    #[test]
+    fn escaped_exclude() {
+        let mut input = Input::new();
+        for c in "\\!a".chars() {
+            input.push(c);
+        }
+        assert!(!input.is_exclude());
+        assert!(input.is_valid());
+        assert_eq!(input.exclude_pattern(), None);
+        assert_eq!(input.pattern(), "!a");
+        input.reset();
+        for c in "a\\!".chars() {
+            input.push(c);
+        }
+        assert_eq!(input.pattern(), "a\\!");
+    }
+
     #[test]
     fn test_render_input() {
         let mut input = Input::new();
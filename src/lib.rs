@@ -36,3 +36,51 @@ pub mod popup;
 
 /// Input widget
 pub mod input;
+
+/// Status line
+pub mod status;
+
+/// Structured lines
+pub mod fields;
+
+/// Numeric values of containers
+pub mod series;
+
+/// Match rates over time
+pub mod rates;
+
+/// Rate based alerts
+pub mod alerts;
+
+/// Trigger commands
+pub mod trigger;
+
+/// Built-in trigger actions
+pub mod actions;
+
+/// Bounded trigger queue
+pub mod queue;
+
+/// Scrollback search
+pub mod search;
+
+/// Boolean expressions of patterns
+pub mod expr;
+
+/// Context lines around matches
+pub mod context;
+
+/// Multi-line events
+pub mod multiline;
+
+/// Session recording and replay
+pub mod replay;
+
+/// Saved sessions
+pub mod session;
+
+/// Buffer exports
+pub mod export;
+
+/// Output files of the containers
+pub mod output;
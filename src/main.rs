@@ -15,7 +15,7 @@ fn main() -> Result<()> {
     let args = parse_args();
     let render_speed = args.render.unwrap_or(100);
 
-    if args.command.is_none() && std::io::stdin().is_terminal() {
+    if args.input_sources().is_empty() && std::io::stdin().is_terminal() {
         eprintln!("No command provided and no data piped.");
         eprintln!("Please pipe some data to this command. Exiting.");
         std::process::exit(1);
@@ -0,0 +1,301 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    style::{Color, Style},
+    text::{Line, Span},
+};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::tstdin::InputLine;
+
+/// Time after which an event with no new line is complete.
+pub const MULTILINE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Default number of lines of an event, the next one starts a new event.
+pub const MULTILINE_MAX_LINES: usize = 500;
+
+/// How the lines of a source are grouped into events, as written in the configuration file.
+///
+/// Either `continuation` or `start` must be set.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct MultilineRule {
+    /// Lines matching it belong to the event before them, e.g. `^\s` or `^\s*at `
+    pub continuation: Option<String>,
+    /// Lines matching it start an event, the others belong to the event before them
+    pub start: Option<String>,
+    /// Lines of an event at most [default: 500]
+    pub max_lines: Option<usize>,
+}
+
+/// Compiled [`MultilineRule`].
+#[derive(Debug, Clone)]
+pub struct Multiline {
+    continuation: Option<Regex>,
+    start: Option<Regex>,
+    max_lines: usize,
+}
+
+impl Multiline {
+    pub fn new(rule: &MultilineRule) -> Result<Self, String> {
+        if rule.continuation.is_none() && rule.start.is_none() {
+            return Err("a multiline rule needs either `continuation` or `start`".to_string());
+        }
+        let compile = |re: &Option<String>| {
+            re.as_deref()
+                .map(Regex::new)
+                .transpose()
+                .map_err(|e| e.to_string())
+        };
+        Ok(Self {
+            continuation: compile(&rule.continuation)?,
+            start: compile(&rule.start)?,
+            max_lines: rule.max_lines.unwrap_or(MULTILINE_MAX_LINES).max(1),
+        })
+    }
+
+    /// Returns true if the line belongs to the event before it
+    fn continues(&self, text: &str) -> bool {
+        let text = text.trim_end_matches(['\r', '\n']);
+        self.continuation
+            .as_ref()
+            .is_some_and(|re| re.is_match(text))
+            || self.start.as_ref().is_some_and(|re| !re.is_match(text))
+    }
+}
+
+/// An event being assembled.
+#[derive(Debug)]
+struct Pending {
+    line: InputLine,
+    lines: usize,
+    updated: Instant,
+}
+
+/// Assembles the lines of each source into events.
+///
+/// The lines of an event are joined with newlines into a single [`InputLine`]. An
+/// event is complete when a line that does not continue it arrives, or when no
+/// line arrives for [`MULTILINE_TIMEOUT`].
+#[derive(Debug, Default)]
+pub struct Grouper {
+    /// rules by source, the default one under `None`
+    rules: HashMap<Option<String>, Multiline>,
+    /// events being assembled by source, and whether they come from the standard error
+    pending: HashMap<(String, bool), Pending>,
+}
+
+impl Grouper {
+    /// Sets the rule of a source, or the default one of every source.
+    pub fn set_rule(&mut self, source: Option<&str>, rule: Multiline) {
+        self.rules.insert(source.map(str::to_string), rule);
+    }
+
+    /// Adds a line, returns the event it completes if any.
+    pub fn push(&mut self, line: InputLine, now: Instant) -> Option<InputLine> {
+        let rule = self
+            .rules
+            .get(&Some(line.source.clone()))
+            .or_else(|| self.rules.get(&None));
+        let Some(rule) = rule else {
+            return Some(line);
+        };
+        let key = (line.source.clone(), line.stderr);
+        if let Some(pending) = self.pending.get_mut(&key) {
+            if pending.lines < rule.max_lines && rule.continues(&line.text) {
+                if !pending.line.text.ends_with('\n') {
+                    pending.line.text.push('\n');
+                }
+                pending.line.text.push_str(&line.text);
+                pending.lines += 1;
+                pending.updated = now;
+                return None;
+            }
+        }
+        let pending = Pending {
+            line,
+            lines: 1,
+            updated: now,
+        };
+        self.pending.insert(key, pending).map(|p| p.line)
+    }
+
+    /// Completes the events of the source.
+    pub fn flush(&mut self, source: &str) -> Vec<InputLine> {
+        self.flush_if(|(s, _), _| s == source)
+    }
+
+    /// Completes the events with no new line since [`MULTILINE_TIMEOUT`].
+    pub fn flush_idle(&mut self, now: Instant) -> Vec<InputLine> {
+        self.flush_if(|_, p| now.duration_since(p.updated) >= MULTILINE_TIMEOUT)
+    }
+
+    /// Completes every event.
+    pub fn flush_all(&mut self) -> Vec<InputLine> {
+        self.flush_if(|_, _| true)
+    }
+
+    fn flush_if<F>(&mut self, done: F) -> Vec<InputLine>
+    where
+        F: Fn(&(String, bool), &Pending) -> bool,
+    {
+        let keys: Vec<(String, bool)> = self
+            .pending
+            .iter()
+            .filter(|(k, p)| done(k, p))
+            .map(|(k, _)| k.clone())
+            .collect();
+        let mut ret: Vec<Pending> = keys.iter().filter_map(|k| self.pending.remove(k)).collect();
+        // The oldest first
+        ret.sort_by_key(|p| p.updated);
+        ret.into_iter().map(|p| p.line).collect()
+    }
+}
+
+/// Splits an entry made of several lines into one [`Line`] per line.
+///
+/// The line break at the end of the entry is left out. Folded entries show their
+/// first line followed by the number of lines hidden.
+pub fn unfold<'a>(entry: &Line<'a>, folded: bool) -> Vec<Line<'a>> {
+    let mut lines = vec![Line::default().style(entry.style)];
+    for span in &entry.spans {
+        let mut parts = span.content.split('\n');
+        if let Some(first) = parts.next() {
+            push_part(lines.last_mut().unwrap(), first, span.style);
+        }
+        for part in parts {
+            let mut line = Line::default().style(entry.style);
+            push_part(&mut line, part, span.style);
+            lines.push(line);
+        }
+    }
+    // The line break at the end of the entry
+    if lines.len() > 1 && lines.last().is_some_and(|l| l.width() == 0) {
+        lines.pop();
+    }
+    if folded && lines.len() > 1 {
+        let hidden = lines.len() - 1;
+        lines.truncate(1);
+        lines[0].spans.push(Span::styled(
+            format!(" [+{hidden} lines]"),
+            Style::default().fg(Color::DarkGray),
+        ));
+    }
+    lines
+}
+
+fn push_part(line: &mut Line, part: &str, style: Style) {
+    if !part.is_empty() {
+        line.spans.push(Span::styled(part.to_string(), style));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tstdin::STDIN_SOURCE as STDIN;
+
+    fn grouper(rule: MultilineRule) -> Grouper {
+        let mut grouper = Grouper::default();
+        grouper.set_rule(None, Multiline::new(&rule).unwrap());
+        grouper
+    }
+
+    fn push_all(grouper: &mut Grouper, lines: &[&str], now: Instant) -> Vec<String> {
+        lines
+            .iter()
+            .filter_map(|l| grouper.push(InputLine::from(format!("{l}\n")), now))
+            .map(|l| l.text)
+            .collect()
+    }
+
+    #[test]
+    fn continuation() {
+        let mut grouper = grouper(MultilineRule {
+            continuation: Some(r"^(\s|Caused by)".to_string()),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let events = push_all(
+            &mut grouper,
+            &[
+                "INFO start",
+                "ERROR boom",
+                "  at a.java:1",
+                "Caused by: x",
+                "  at b.java:2",
+                "INFO done",
+            ],
+            now,
+        );
+        assert_eq!(
+            events,
+            vec![
+                "INFO start\n",
+                "ERROR boom\n  at a.java:1\nCaused by: x\n  at b.java:2\n"
+            ]
+        );
+        assert!(grouper.flush_idle(now).is_empty());
+        let idle = grouper.flush_idle(now + MULTILINE_TIMEOUT);
+        assert_eq!(idle[0].text, "INFO done\n");
+        assert!(grouper.flush_all().is_empty());
+    }
+
+    #[test]
+    fn start_and_max_lines() {
+        let mut grouper = grouper(MultilineRule {
+            start: Some(r"^\d{4}-".to_string()),
+            max_lines: Some(3),
+            ..Default::default()
+        });
+        let now = Instant::now();
+        let events = push_all(
+            &mut grouper,
+            &["2024- a", "b", "2024- c", "d", "e", "f", "2024- g"],
+            now,
+        );
+        assert_eq!(events, vec!["2024- a\nb\n", "2024- c\nd\ne\n", "f\n"]);
+        // Sources and their standard error are grouped apart
+        grouper.push(InputLine::new_stderr(STDIN, "h".to_string()), now);
+        grouper.push(InputLine::new("other", "i".to_string()), now);
+        assert_eq!(grouper.flush(STDIN).len(), 2);
+        assert_eq!(grouper.flush_all()[0].text, "i");
+
+        assert!(Multiline::new(&MultilineRule::default()).is_err());
+        let rule = MultilineRule {
+            start: Some("(".to_string()),
+            ..Default::default()
+        };
+        assert!(Multiline::new(&rule).is_err());
+    }
+
+    #[test]
+    fn unfold_entry() {
+        let red = Style::default().fg(Color::Red);
+        let entry = Line::from(vec![
+            Span::styled("ERROR", red),
+            Span::raw(" boom\n  at a\n"),
+            Span::styled("  at b\n", red),
+        ]);
+        let lines = unfold(&entry, false);
+        assert_eq!(
+            lines,
+            vec![
+                Line::from(vec![Span::styled("ERROR", red), Span::raw(" boom")]),
+                Line::from("  at a"),
+                Line::from(Span::styled("  at b", red)),
+            ]
+        );
+        let folded = unfold(&entry, true);
+        assert_eq!(folded.len(), 1);
+        assert_eq!(folded[0].to_string(), "ERROR boom [+2 lines]");
+        let single = Line::from(Span::styled("one line\n", red));
+        assert_eq!(
+            unfold(&single, true),
+            vec![Line::from(Span::styled("one line", red))]
+        );
+    }
+}
@@ -0,0 +1,548 @@
+use std::{
+    fmt,
+    fs::{self, File, OpenOptions},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    str::FromStr,
+    thread::{self, JoinHandle},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::fields::parse_number;
+
+/// Name of the output files unless told otherwise.
+pub const OUTPUT_TEMPLATE: &str = "{container}.txt";
+
+/// How often the lines written to the output files are flushed.
+pub const OUTPUT_FLUSH: Duration = Duration::from_secs(1);
+
+/// Placeholders of the name of the output files.
+const PLACEHOLDERS: [&str; 3] = ["{date}", "{container}", "{id}"];
+
+/// When the output files are rotated, as written in the configuration file.
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RotateRule {
+    /// Size the files are rotated at, e.g. `10M`
+    pub size: Option<Size>,
+    /// Time the files are rotated after, e.g. `1h`
+    pub every: Option<Interval>,
+    /// Compress the rotated files with gzip
+    pub gzip: Option<bool>,
+}
+
+impl RotateRule {
+    pub fn is_empty(&self) -> bool {
+        self.size.is_none() && self.every.is_none() && self.gzip.is_none()
+    }
+}
+
+/// A number of bytes, `1048576`, `512K`, `10M` or `1G`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "RawValue", into = "String")]
+pub struct Size(pub u64);
+
+const UNITS: [(&str, u64); 3] = [("G", 1 << 30), ("M", 1 << 20), ("K", 1 << 10)];
+
+impl FromStr for Size {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let upper = s.trim().to_uppercase();
+        let number = upper.trim_end_matches('B');
+        let (number, unit) = UNITS
+            .iter()
+            .find_map(|(suffix, unit)| Some((number.strip_suffix(suffix)?, *unit)))
+            .unwrap_or((number, 1));
+        match number.parse::<u64>() {
+            Ok(n) if n > 0 => Ok(Self(n.saturating_mul(unit))),
+            _ => Err(format!("`{s}` isn't a valid size")),
+        }
+    }
+}
+
+impl fmt::Display for Size {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match UNITS.iter().find(|(_, unit)| self.0 % unit == 0) {
+            Some((suffix, unit)) => write!(f, "{}{suffix}", self.0 / unit),
+            None => write!(f, "{}", self.0),
+        }
+    }
+}
+
+impl From<Size> for String {
+    fn from(size: Size) -> Self {
+        size.to_string()
+    }
+}
+
+/// A time span, `3600` seconds, `90m` or `1h`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(try_from = "RawValue", into = "String")]
+pub struct Interval(pub Duration);
+
+impl FromStr for Interval {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match parse_number(s) {
+            Some(secs) if secs.is_finite() && secs > 0.0 => Ok(Self(Duration::from_secs_f64(secs))),
+            _ => Err(format!("`{s}` isn't a valid interval")),
+        }
+    }
+}
+
+impl fmt::Display for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}s", self.0.as_secs_f64())
+    }
+}
+
+impl From<Interval> for String {
+    fn from(interval: Interval) -> Self {
+        interval.to_string()
+    }
+}
+
+/// Size or interval as written in the configuration file, a number or a string.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawValue {
+    Number(u64),
+    Text(String),
+}
+
+impl TryFrom<RawValue> for Size {
+    type Error = String;
+
+    fn try_from(raw: RawValue) -> Result<Self, Self::Error> {
+        match raw {
+            RawValue::Number(n) => n.to_string().parse(),
+            RawValue::Text(text) => text.parse(),
+        }
+    }
+}
+
+impl TryFrom<RawValue> for Interval {
+    type Error = String;
+
+    fn try_from(raw: RawValue) -> Result<Self, Self::Error> {
+        match raw {
+            RawValue::Number(n) => n.to_string().parse(),
+            RawValue::Text(text) => text.parse(),
+        }
+    }
+}
+
+/// Checks that the template only has known placeholders and names a file.
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or(format!("unclosed placeholder in `{template}`"))?;
+        let placeholder = &rest[start..=start + end];
+        if !PLACEHOLDERS.contains(&placeholder) {
+            return Err(format!("unknown placeholder {placeholder} in `{template}`"));
+        }
+        rest = &rest[start + end + 1..];
+    }
+    if template.is_empty() || template.ends_with('/') {
+        return Err(format!("`{template}` isn't a valid file name"));
+    }
+    Ok(())
+}
+
+/// Name of the output file of a container, `{date}` is the UTC date.
+pub fn render_template(template: &str, id: u8, container: &str, now: SystemTime) -> String {
+    let mut ret = template
+        .replace("{container}", container)
+        .replace("{id}", &id.to_string());
+    if ret.contains("{date}") {
+        ret = ret.replace("{date}", &date(now));
+    }
+    ret
+}
+
+/// `YYYY-MM-DD` of the time, in UTC.
+fn date(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    // Days since 0000-03-01, years starting in March put leap days at their end
+    let days = secs / 86400 + 719468;
+    let era = days / 146097;
+    let day_of_era = days % 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/// File the matching lines of a container are written to.
+///
+/// Lines are buffered and flushed every [`OUTPUT_FLUSH`]. Once the file reaches the
+/// size or the age of the rotation rule it is renamed to `<name>.<n>`, the first `n`
+/// free, and optionally compressed, and a new one is started. When the name has a
+/// `{date}` the file is also rotated each day, under its new name.
+#[derive(Debug)]
+pub struct OutputFile {
+    dir: PathBuf,
+    template: String,
+    id: u8,
+    container: String,
+    rotate: RotateRule,
+    path: PathBuf,
+    writer: BufWriter<File>,
+    /// bytes in the file
+    size: u64,
+    opened: Instant,
+    flushed: Instant,
+    /// gzip running on rotated files
+    compressing: Vec<JoinHandle<io::Result<()>>>,
+}
+
+impl OutputFile {
+    pub fn open(
+        dir: &Path,
+        template: &str,
+        id: u8,
+        container: &str,
+        rotate: &RotateRule,
+    ) -> io::Result<Self> {
+        let path = dir.join(render_template(template, id, container, SystemTime::now()));
+        let (writer, size) = open(&path)
+            .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+        let now = Instant::now();
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            template: template.to_string(),
+            id,
+            container: container.to_string(),
+            rotate: rotate.clone(),
+            path,
+            writer,
+            size,
+            opened: now,
+            flushed: now,
+            compressing: vec![],
+        })
+    }
+
+    /// Path of the file being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes the line, rotating the file first if it is due.
+    pub fn write(&mut self, line: &str, now: Instant) -> io::Result<()> {
+        let len = line.len() as u64;
+        let full = self
+            .rotate
+            .size
+            .is_some_and(|max| self.size > 0 && self.size + len > max.0);
+        let rotated = if full || self.expired(now) {
+            self.rotate(now)
+        } else {
+            self.follow_date(now)
+        };
+        // The line still goes to the file in use when the rotation failed
+        self.writer.write_all(line.as_bytes())?;
+        self.size += len;
+        rotated
+    }
+
+    /// Flushes the lines written, rotates the file if it is too old and reports
+    /// rotated files that failed to be compressed.
+    pub fn tick(&mut self, now: Instant) -> io::Result<()> {
+        let mut ret = if self.expired(now) {
+            self.rotate(now)
+        } else {
+            self.follow_date(now)
+        };
+        if ret.is_ok() && now.duration_since(self.flushed) >= OUTPUT_FLUSH {
+            ret = self.flush(now);
+        }
+        let (done, running) = self
+            .compressing
+            .drain(..)
+            .partition(|job| job.is_finished());
+        self.compressing = running;
+        for job in done {
+            let compressed = job
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("gzip panicked")));
+            ret = ret.and(compressed);
+        }
+        ret
+    }
+
+    pub fn flush(&mut self, now: Instant) -> io::Result<()> {
+        self.flushed = now;
+        self.writer.flush()
+    }
+
+    fn expired(&self, now: Instant) -> bool {
+        self.size > 0
+            && self
+                .rotate
+                .every
+                .is_some_and(|every| now.duration_since(self.opened) >= every.0)
+    }
+
+    /// Moves the file aside and starts a new one.
+    ///
+    /// The new one is started even if the file couldn't be moved, e.g. because it
+    /// was deleted, so that a failed rotation is reported once and not on every line.
+    fn rotate(&mut self, now: Instant) -> io::Result<()> {
+        let rotated = rotated_path(&self.path);
+        let moved = self
+            .flush(now)
+            .and_then(|()| fs::rename(&self.path, &rotated));
+        if moved.is_ok() {
+            self.compress(rotated);
+        }
+        let path = self.current_path();
+        let reopened = self.reopen(path, now);
+        moved.and(reopened)
+    }
+
+    /// Starts the file of the day when the name has a `{date}`.
+    fn follow_date(&mut self, now: Instant) -> io::Result<()> {
+        if !self.template.contains("{date}") {
+            return Ok(());
+        }
+        let path = self.current_path();
+        if path == self.path {
+            return Ok(());
+        }
+        self.flush(now)?;
+        let previous = std::mem::replace(&mut self.path, path.clone());
+        self.compress(previous);
+        self.reopen(path, now)
+    }
+
+    fn current_path(&self) -> PathBuf {
+        let name = render_template(&self.template, self.id, &self.container, SystemTime::now());
+        self.dir.join(name)
+    }
+
+    fn reopen(&mut self, path: PathBuf, now: Instant) -> io::Result<()> {
+        let (writer, size) = open(&path)?;
+        self.path = path;
+        self.writer = writer;
+        self.size = size;
+        self.opened = now;
+        Ok(())
+    }
+
+    fn compress(&mut self, path: PathBuf) {
+        if self.rotate.gzip != Some(true) {
+            return;
+        }
+        self.compressing.push(thread::spawn(move || {
+            let status = Command::new("gzip")
+                .arg("-f")
+                .arg(&path)
+                .status()
+                .map_err(|e| io::Error::new(e.kind(), format!("gzip: {e}")))?;
+            if !status.success() {
+                let message = format!("gzip {} failed: {status}", path.display());
+                return Err(io::Error::other(message));
+            }
+            Ok(())
+        }));
+    }
+}
+
+fn open(path: &Path) -> io::Result<(BufWriter<File>, u64)> {
+    let file = OpenOptions::new().append(true).create(true).open(path)?;
+    let size = file.metadata()?.len();
+    Ok((BufWriter::new(file), size))
+}
+
+/// `<path>.<n>` with the first `n` that is free, compressed or not.
+fn rotated_path(path: &Path) -> PathBuf {
+    let name = path.as_os_str().to_string_lossy();
+    (1..)
+        .map(|n| PathBuf::from(format!("{name}.{n}")))
+        .find(|p| !p.exists() && !PathBuf::from(format!("{}.gz", p.display())).exists())
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("logss-output-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn template() {
+        let time = UNIX_EPOCH + Duration::from_secs(1_709_210_096);
+        assert_eq!(
+            render_template("{date}-{container}-{id}.log", 3, "error", time),
+            "2024-02-29-error-3.log"
+        );
+        assert_eq!(date(UNIX_EPOCH), "1970-01-01");
+        assert_eq!(
+            date(UNIX_EPOCH + Duration::from_secs(951_782_400)),
+            "2000-02-29"
+        );
+        assert_eq!(render_template(OUTPUT_TEMPLATE, 1, "get", time), "get.txt");
+        assert!(validate_template("{date}-{container}-{id}.log").is_ok());
+        for template in ["{name}.log", "{date.log", "", "logs/"] {
+            assert!(validate_template(template).is_err(), "{template}");
+        }
+    }
+
+    #[test]
+    fn size_and_interval() {
+        assert_eq!("10M".parse(), Ok(Size(10 << 20)));
+        assert_eq!("512kb".parse(), Ok(Size(512 << 10)));
+        assert_eq!("100".parse(), Ok(Size(100)));
+        for size in ["0", "-1", "10X", "M"] {
+            assert!(size.parse::<Size>().is_err(), "{size}");
+        }
+        assert_eq!(Size(1 << 30).to_string(), "1G");
+        assert_eq!(Size(1000).to_string(), "1000");
+        assert_eq!(serde_yaml::from_str::<Size>("2048").unwrap(), Size(2048));
+        assert_eq!(serde_yaml::from_str::<Size>("2K").unwrap(), Size(2048));
+
+        assert_eq!("1h".parse(), Ok(Interval(Duration::from_secs(3600))));
+        assert_eq!("90".parse(), Ok(Interval(Duration::from_secs(90))));
+        assert!("0".parse::<Interval>().is_err());
+        assert!("soon".parse::<Interval>().is_err());
+        let interval: Interval = serde_yaml::from_str("30m").unwrap();
+        assert_eq!(interval.to_string().parse(), Ok(interval));
+    }
+
+    #[test]
+    fn rotate_by_size() {
+        let dir = temp_dir("size");
+        let rule = RotateRule {
+            size: Some(Size(10)),
+            ..Default::default()
+        };
+        let mut output = OutputFile::open(&dir, "{id}-{container}.log", 2, "get", &rule).unwrap();
+        assert_eq!(output.path(), dir.join("2-get.log"));
+        let now = Instant::now();
+        for line in ["first\n", "second\n", "third\n"] {
+            output.write(line, now).unwrap();
+        }
+        // Nothing is written until the lines are flushed
+        assert_eq!(fs::read_to_string(dir.join("2-get.log")).unwrap(), "");
+        output.tick(now + OUTPUT_FLUSH).unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.join("2-get.log.1")).unwrap(),
+            "first\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("2-get.log.2")).unwrap(),
+            "second\n"
+        );
+        assert_eq!(
+            fs::read_to_string(dir.join("2-get.log")).unwrap(),
+            "third\n"
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_by_time() {
+        let dir = temp_dir("time");
+        let rule = RotateRule {
+            every: Some(Interval(Duration::from_secs(60))),
+            ..Default::default()
+        };
+        let mut output = OutputFile::open(&dir, OUTPUT_TEMPLATE, 1, "get", &rule).unwrap();
+        let now = Instant::now();
+        output.write("old\n", now).unwrap();
+        output.tick(now + Duration::from_secs(30)).unwrap();
+        assert!(!dir.join("get.txt.1").exists());
+        output.tick(now + Duration::from_secs(60)).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("get.txt.1")).unwrap(), "old\n");
+        // An empty file is not rotated
+        output.tick(now + Duration::from_secs(180)).unwrap();
+        assert!(!dir.join("get.txt.2").exists());
+        output
+            .write("new\n", now + Duration::from_secs(180))
+            .unwrap();
+        output.flush(now).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("get.txt")).unwrap(), "new\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotate_gzip() {
+        let dir = temp_dir("gzip");
+        let rule = RotateRule {
+            size: Some(Size(1)),
+            gzip: Some(true),
+            ..Default::default()
+        };
+        let mut output = OutputFile::open(&dir, OUTPUT_TEMPLATE, 1, "get", &rule).unwrap();
+        let now = Instant::now();
+        output.write("a\n", now).unwrap();
+        output.write("b\n", now).unwrap();
+        while !output.compressing.is_empty() {
+            thread::sleep(Duration::from_millis(10));
+            output.tick(now).unwrap();
+        }
+        assert!(dir.join("get.txt.1.gz").exists());
+        assert!(!dir.join("get.txt.1").exists());
+        assert_eq!(rotated_path(&dir.join("get.txt")), dir.join("get.txt.2"));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn write_errors() {
+        let dir = temp_dir("errors");
+        let missing = dir.join("missing");
+        assert!(
+            OutputFile::open(&missing, OUTPUT_TEMPLATE, 1, "get", &RotateRule::default()).is_err()
+        );
+        let rule = RotateRule {
+            size: Some(Size(1)),
+            ..Default::default()
+        };
+        let mut output = OutputFile::open(&dir, OUTPUT_TEMPLATE, 1, "get", &rule).unwrap();
+        output.write("a\n", Instant::now()).unwrap();
+        fs::remove_dir_all(&dir).unwrap();
+        // The file can't be rotated once its directory is gone
+        assert!(output.write("b\n", Instant::now()).is_err());
+    }
+
+    #[test]
+    fn rotate_deleted() {
+        let dir = temp_dir("deleted");
+        let rule = RotateRule {
+            size: Some(Size(3)),
+            ..Default::default()
+        };
+        let mut output = OutputFile::open(&dir, OUTPUT_TEMPLATE, 1, "get", &rule).unwrap();
+        let path = output.path().to_path_buf();
+        output.write("a\n", Instant::now()).unwrap();
+        output.flush(Instant::now()).unwrap();
+        fs::remove_file(&path).unwrap();
+        // The rotation is reported but the file is started again
+        assert!(output.write("bb\n", Instant::now()).is_err());
+        output.write("c\n", Instant::now()).unwrap();
+        output.flush(Instant::now()).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("get.txt.1")).unwrap(), "bb\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "c\n");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -2,7 +2,7 @@ use ratatui::{
     layout::{Constraint, Direction, Layout, Rect},
     prelude::style::{Color, Style},
     text::Line,
-    widgets::{BarChart, Block, Borders, Clear, Paragraph},
+    widgets::{BarChart, Block, Borders, Chart, Clear, Paragraph},
     Frame,
 };
 
@@ -25,6 +25,14 @@ pub fn render_bar_chart_popup(frame: &mut Frame<'_>, barchart: BarChart, percent
     frame.render_widget(barchart, area);
 }
 
+pub fn render_chart_popup(frame: &mut Frame<'_>, chart: Chart, percent_area: (u16, u16)) {
+    let size = frame.area();
+    let area = centered_rect(percent_area.0, percent_area.1, size);
+
+    frame.render_widget(Clear, area); // this clears out the background
+    frame.render_widget(chart, area);
+}
+
 /// helper function to create a centered rect using up certain percentage of the available rect `r`
 pub fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = Layout::default()
@@ -0,0 +1,402 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    str::FromStr,
+    sync::{mpsc::Sender, Arc, Condvar, Mutex, MutexGuard},
+    thread,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::trigger::{self, TriggerEvent, TriggerInput, TriggerOutcome, TriggerStatus};
+
+/// Default number of triggers waiting to run per container.
+pub const TRIGGER_QUEUE: usize = 100;
+
+/// Longest time a trigger waits for room with [`Overflow::Block`] before it is dropped.
+///
+/// The wait happens while the input is processed, so it stalls the interface too.
+pub const TRIGGER_BLOCK: Duration = Duration::from_millis(500);
+
+/// What happens to a trigger when the queue is full.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Overflow {
+    /// The new trigger is discarded
+    #[default]
+    Drop,
+    /// The waiting triggers are merged into the new one
+    Coalesce,
+    /// The caller waits until there is room, slowing down the input, for up to
+    /// [`TRIGGER_BLOCK`] before the trigger is dropped
+    Block,
+}
+
+impl FromStr for Overflow {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "drop" => Ok(Self::Drop),
+            "coalesce" => Ok(Self::Coalesce),
+            "block" => Ok(Self::Block),
+            _ => Err(format!("`{s}` isn't a valid overflow policy")),
+        }
+    }
+}
+
+/// Depth and losses of one or more queues.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct QueueStats {
+    /// triggers waiting to run
+    pub queued: usize,
+    /// triggers running
+    pub running: usize,
+    /// highest number of triggers waiting at once in a single queue
+    pub peak: usize,
+    pub dropped: u64,
+    /// triggers merged into a later one
+    pub coalesced: u64,
+}
+
+impl QueueStats {
+    /// Adds the stats of another queue, keeping the highest peak of both.
+    pub fn add(&mut self, other: &Self) {
+        self.queued += other.queued;
+        self.running += other.running;
+        self.peak = self.peak.max(other.peak);
+        self.dropped += other.dropped;
+        self.coalesced += other.coalesced;
+    }
+}
+
+impl fmt::Display for QueueStats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} queued (peak {}), {} running, {} dropped, {} coalesced",
+            self.queued, self.peak, self.running, self.dropped, self.coalesced
+        )
+    }
+}
+
+/// A trigger waiting to run.
+#[derive(Debug, Clone)]
+pub struct TriggerJob {
+    /// id of the container
+    pub id: u8,
+    pub command: String,
+    pub input: TriggerInput,
+    pub timeout: Duration,
+    pub event: TriggerEvent,
+    /// where the outcome is sent
+    pub results: Option<Sender<TriggerOutcome>>,
+}
+
+impl TriggerJob {
+    /// Spawns the command and waits for it.
+    pub fn run(self) {
+        let outcome = match trigger::spawn(&self.command, self.input, &self.event) {
+            Ok(child) => trigger::wait(child, self.id, self.event, self.timeout),
+            Err(e) => {
+                TriggerOutcome::new(self.id, self.event, TriggerStatus::Failed(e.to_string()))
+            }
+        };
+        if let Some(results) = self.results {
+            results.send(outcome).ok();
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct State {
+    jobs: VecDeque<TriggerJob>,
+    stats: QueueStats,
+    closed: bool,
+    /// a blocked trigger was dropped, the next ones are too until a trigger starts
+    stalled: bool,
+}
+
+#[derive(Debug, Default)]
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wait<'a>(&self, guard: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed.wait(guard).unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn wait_timeout<'a>(
+        &self,
+        guard: MutexGuard<'a, State>,
+        timeout: Duration,
+    ) -> MutexGuard<'a, State> {
+        self.changed
+            .wait_timeout(guard, timeout)
+            .map_or_else(|e| e.into_inner().0, |(guard, _)| guard)
+    }
+}
+
+/// Bounded queue of triggers run by its own worker threads.
+///
+/// Triggers are spawned and waited for on the workers, so a burst of matches
+/// never stalls the caller unless the overflow policy is [`Overflow::Block`].
+#[derive(Debug)]
+pub struct TriggerQueue {
+    shared: Arc<Shared>,
+    /// triggers that can wait at once
+    pub capacity: usize,
+    pub overflow: Overflow,
+}
+
+impl TriggerQueue {
+    pub fn new(threads: usize, capacity: usize, overflow: Overflow) -> Self {
+        let shared = Arc::new(Shared::default());
+        for _ in 0..threads.max(1) {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || work(&shared));
+        }
+        Self {
+            shared,
+            capacity,
+            overflow,
+        }
+    }
+
+    /// Queues a trigger, applying the overflow policy if the queue is full.
+    pub fn push(&self, mut job: TriggerJob) {
+        let capacity = self.capacity.max(1);
+        let mut state = self.shared.lock();
+        if state.jobs.len() >= capacity {
+            match self.overflow {
+                Overflow::Drop => {
+                    state.stats.dropped += 1;
+                    return;
+                }
+                Overflow::Coalesce => {
+                    let merged: usize = state.jobs.iter().map(|j| j.event.coalesced + 1).sum();
+                    job.event.coalesced += merged;
+                    state.stats.coalesced += merged as u64;
+                    state.jobs.clear();
+                }
+                Overflow::Block => {
+                    let deadline = Instant::now() + TRIGGER_BLOCK;
+                    while state.jobs.len() >= capacity && !state.closed && !state.stalled {
+                        let left = deadline.saturating_duration_since(Instant::now());
+                        if left.is_zero() {
+                            state.stalled = true;
+                            break;
+                        }
+                        state = self.shared.wait_timeout(state, left);
+                    }
+                    if state.jobs.len() >= capacity {
+                        state.stats.dropped += 1;
+                        return;
+                    }
+                }
+            }
+        }
+        state.jobs.push_back(job);
+        state.stats.peak = state.stats.peak.max(state.jobs.len());
+        self.shared.changed.notify_all();
+    }
+
+    pub fn stats(&self) -> QueueStats {
+        let state = self.shared.lock();
+        QueueStats {
+            queued: state.jobs.len(),
+            ..state.stats
+        }
+    }
+
+    /// Waits until every queued trigger has run.
+    pub fn join(&self) {
+        let mut state = self.shared.lock();
+        while !state.jobs.is_empty() || state.stats.running > 0 {
+            state = self.shared.wait(state);
+        }
+    }
+}
+
+impl Drop for TriggerQueue {
+    /// Stops the workers, the triggers that did not start are discarded.
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+fn work(shared: &Shared) {
+    loop {
+        let job = {
+            let mut state = shared.lock();
+            loop {
+                if state.closed {
+                    return;
+                }
+                if let Some(job) = state.jobs.pop_front() {
+                    state.stats.running += 1;
+                    state.stalled = false;
+                    break job;
+                }
+                state = shared.wait(state);
+            }
+        };
+        // Room for a blocked caller
+        shared.changed.notify_all();
+        job.run();
+        shared.lock().stats.running -= 1;
+        shared.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::mpsc, time::Instant};
+
+    use super::*;
+
+    fn job(line: &str, command: &str, results: &Sender<TriggerOutcome>) -> TriggerJob {
+        TriggerJob {
+            id: 1,
+            command: command.to_string(),
+            input: TriggerInput::Safe,
+            timeout: Duration::from_secs(5),
+            event: TriggerEvent::new(line, "re"),
+            results: Some(results.clone()),
+        }
+    }
+
+    /// Queue whose only worker is busy until `release` is removed.
+    fn busy_queue(
+        capacity: usize,
+        overflow: Overflow,
+        results: &Sender<TriggerOutcome>,
+    ) -> (TriggerQueue, std::path::PathBuf) {
+        let release = std::env::temp_dir().join(format!(
+            "logss-queue-{}-{overflow:?}-{capacity}",
+            std::process::id()
+        ));
+        std::fs::write(&release, "").unwrap();
+        let queue = TriggerQueue::new(1, capacity, overflow);
+        let wait = format!("while [ -e {} ]; do sleep 0.01; done", release.display());
+        queue.push(job("busy", &wait, results));
+        while queue.stats().running == 0 {
+            thread::sleep(Duration::from_millis(5));
+        }
+        (queue, release)
+    }
+
+    #[test]
+    fn drop_and_coalesce() {
+        let (sender, receiver) = mpsc::channel();
+        let (queue, release) = busy_queue(2, Overflow::Drop, &sender);
+        for line in ["a", "b", "c"] {
+            queue.push(job(line, "echo \"$LOGSS_COALESCED\"", &sender));
+        }
+        let stats = queue.stats();
+        assert_eq!((stats.queued, stats.running, stats.dropped), (2, 1, 1));
+        std::fs::remove_file(&release).unwrap();
+        queue.join();
+        let lines: Vec<String> = receiver.try_iter().map(|o| o.event.line).collect();
+        assert_eq!(lines, vec!["busy", "a", "b"]);
+
+        let (queue, release) = busy_queue(2, Overflow::Coalesce, &sender);
+        for line in ["a", "b", "c", "d"] {
+            queue.push(job(line, "echo \"$LOGSS_COALESCED\"", &sender));
+        }
+        assert_eq!(queue.stats().coalesced, 2);
+        assert_eq!(queue.stats().peak, 2);
+        std::fs::remove_file(&release).unwrap();
+        queue.join();
+        let outcomes: Vec<(String, String)> = receiver
+            .try_iter()
+            .map(|o| (o.event.line, o.stdout))
+            .collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                ("busy".to_string(), String::new()),
+                ("c".to_string(), "2".to_string()),
+                ("d".to_string(), "0".to_string())
+            ]
+        );
+    }
+
+    #[test]
+    fn stats_add() {
+        let mut stats = QueueStats {
+            queued: 1,
+            peak: 3,
+            dropped: 1,
+            ..Default::default()
+        };
+        stats.add(&QueueStats {
+            queued: 2,
+            running: 1,
+            peak: 2,
+            coalesced: 4,
+            ..Default::default()
+        });
+        assert_eq!(
+            stats,
+            QueueStats {
+                queued: 3,
+                running: 1,
+                peak: 3,
+                dropped: 1,
+                coalesced: 4,
+            }
+        );
+    }
+
+    #[test]
+    fn block() {
+        let (sender, receiver) = mpsc::channel();
+        let (queue, release) = busy_queue(1, Overflow::Block, &sender);
+        queue.push(job("a", "true", &sender));
+        let remover = {
+            let release = release.clone();
+            thread::spawn(move || {
+                thread::sleep(Duration::from_millis(200));
+                std::fs::remove_file(release).unwrap();
+            })
+        };
+        let started = Instant::now();
+        queue.push(job("b", "true", &sender));
+        assert!(started.elapsed() >= Duration::from_millis(100));
+        remover.join().unwrap();
+        queue.join();
+        assert_eq!(receiver.try_iter().count(), 3);
+        assert_eq!(queue.stats().dropped, 0);
+        assert_eq!("coalesce".parse(), Ok(Overflow::Coalesce));
+        assert!("wait".parse::<Overflow>().is_err());
+    }
+
+    #[test]
+    fn block_timeout() {
+        let (sender, receiver) = mpsc::channel();
+        let (queue, release) = busy_queue(2, Overflow::Block, &sender);
+        queue.push(job("a", "true", &sender));
+        queue.push(job("a", "true", &sender));
+        let started = Instant::now();
+        queue.push(job("b", "true", &sender));
+        assert!(started.elapsed() >= TRIGGER_BLOCK);
+        // Dropped right away while the queue stays full
+        let started = Instant::now();
+        queue.push(job("c", "true", &sender));
+        assert!(started.elapsed() < TRIGGER_BLOCK);
+        assert_eq!(queue.stats().dropped, 2);
+        std::fs::remove_file(&release).unwrap();
+        queue.join();
+        let lines: Vec<String> = receiver.try_iter().map(|o| o.event.line).collect();
+        assert_eq!(lines, vec!["busy", "a", "a"]);
+    }
+}
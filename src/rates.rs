@@ -0,0 +1,196 @@
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    style::{Color, Style},
+    symbols::Marker,
+    text::Line,
+    widgets::{Axis, Block, Borders, Chart, Dataset, GraphType},
+    Frame,
+};
+
+use crate::{app::App, popup::render_chart_popup};
+
+/// Seconds of history kept per container.
+pub const RATE_HISTORY: usize = 3600;
+/// Number of buckets shown in the chart.
+pub const RATE_BUCKETS: usize = 60;
+/// Selectable bucket sizes, in seconds.
+pub const RATE_BUCKET_SIZES: [u64; 3] = [1, 10, 60];
+
+/// Matches per second over the last [`RATE_HISTORY`] seconds.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Rates {
+    /// time of the first match
+    start: Option<Instant>,
+    /// second, since `start`, of the newest count
+    last: u64,
+    counts: VecDeque<u64>,
+}
+
+impl Rates {
+    pub fn new(start: Instant) -> Self {
+        Self {
+            start: Some(start),
+            last: 0,
+            counts: VecDeque::from([0]),
+        }
+    }
+
+    fn second(&self, now: Instant) -> u64 {
+        self.start
+            .map_or(0, |start| now.saturating_duration_since(start).as_secs())
+    }
+
+    /// Counts a match at `now`.
+    pub fn record(&mut self, now: Instant) {
+        if self.start.is_none() {
+            *self = Self::new(now);
+        }
+        let second = self.second(now);
+        if second > self.last {
+            let gap = (second - self.last).min(RATE_HISTORY as u64);
+            for _ in 0..gap {
+                self.counts.push_back(0);
+            }
+            self.counts
+                .drain(..self.counts.len().saturating_sub(RATE_HISTORY));
+            self.last = second;
+        }
+        if let Some(count) = self.counts.back_mut() {
+            *count += 1;
+        }
+    }
+
+    fn count_at(&self, second: u64) -> u64 {
+        if self.counts.is_empty() || second > self.last {
+            return 0;
+        }
+        let offset = (self.last - second) as usize;
+        if offset >= self.counts.len() {
+            return 0;
+        }
+        self.counts[self.counts.len() - 1 - offset]
+    }
+
+    /// Matches per second of the last `buckets` buckets of `size`, the newest last.
+    pub fn rates(&self, now: Instant, size: Duration, buckets: usize) -> Vec<f64> {
+        let size = size.as_secs().max(1);
+        let now = self.second(now);
+        (0..buckets as u64)
+            .rev()
+            .map(|bucket| {
+                let newest = now.checked_sub(bucket * size);
+                let total: u64 = newest
+                    .map(|newest| {
+                        (newest.saturating_sub(size - 1)..=newest)
+                            .map(|second| self.count_at(second))
+                            .sum()
+                    })
+                    .unwrap_or(0);
+                total as f64 / size as f64
+            })
+            .collect()
+    }
+}
+
+/// Renders the match rate of every container as a line chart.
+pub fn render_rate_chart(frame: &mut Frame, app: &App) {
+    let size = RATE_BUCKET_SIZES[app.state.rate_bucket % RATE_BUCKET_SIZES.len()];
+    let now = Instant::now();
+    let points: Vec<Vec<(f64, f64)>> = app
+        .containers
+        .iter()
+        .map(|c| {
+            c.state
+                .rates
+                .rates(now, Duration::from_secs(size), RATE_BUCKETS)
+                .into_iter()
+                .enumerate()
+                .map(|(i, rate)| ((i as f64 + 1.0 - RATE_BUCKETS as f64) * size as f64, rate))
+                .collect()
+        })
+        .collect();
+    let max = points
+        .iter()
+        .flatten()
+        .map(|(_, rate)| *rate)
+        .fold(1.0, f64::max);
+    let datasets = app
+        .containers
+        .iter()
+        .zip(points.iter())
+        .map(|(c, points)| {
+            Dataset::default()
+                .name(c.text.clone())
+                .marker(Marker::Braille)
+                .graph_type(GraphType::Line)
+                .style(Style::default().fg(c.state.color))
+                .data(points)
+        })
+        .collect();
+    let window = size * (RATE_BUCKETS as u64 - 1);
+    let title = format!("Rates (lines/s, {size}s buckets)");
+    let chart = Chart::new(datasets)
+        .block(Block::default().title(title).borders(Borders::ALL))
+        .style(Style::default().fg(Color::White).bg(Color::Black))
+        .x_axis(
+            Axis::default()
+                .bounds([-(window as f64), 0.0])
+                .labels([Line::from(format!("-{window}s")), Line::from("now")]),
+        )
+        .y_axis(
+            Axis::default()
+                .bounds([0.0, max])
+                .labels([Line::from("0"), Line::from(format!("{max:.1}"))]),
+        );
+    render_chart_popup(frame, chart, (80, 50));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rates() {
+        let start = Instant::now();
+        let at = |secs: u64| start + Duration::from_millis(secs * 1000 + 500);
+        let mut rates = Rates::new(start);
+        rates.record(at(0));
+        rates.record(at(0));
+        rates.record(at(2));
+        rates.record(at(5));
+        rates.record(at(5));
+        rates.record(at(5));
+
+        let second = Duration::from_secs(1);
+        assert_eq!(
+            rates.rates(at(5), second, 7),
+            vec![0.0, 2.0, 0.0, 1.0, 0.0, 0.0, 3.0]
+        );
+        // Nothing happened since
+        assert_eq!(rates.rates(at(7), second, 3), vec![3.0, 0.0, 0.0]);
+        // Buckets of 3 seconds: [0-2], [3-5]
+        assert_eq!(
+            rates.rates(at(5), Duration::from_secs(3), 3),
+            vec![0.0, 1.0, 1.0]
+        );
+        assert_eq!(rates.counts.len(), 6);
+
+        // Older seconds are forgotten
+        rates.record(at(RATE_HISTORY as u64 + 1));
+        assert_eq!(rates.counts.len(), RATE_HISTORY);
+        assert_eq!(rates.count_at(0), 0);
+        assert_eq!(rates.count_at(5), 3);
+        rates.record(at(10 * RATE_HISTORY as u64));
+        assert_eq!(rates.counts.len(), RATE_HISTORY);
+        assert_eq!(rates.count_at(5), 0);
+
+        let mut rates = Rates::default();
+        assert_eq!(rates.rates(at(0), second, 2), vec![0.0, 0.0]);
+        rates.record(at(3));
+        assert_eq!(rates.rates(at(3), second, 2), vec![0.0, 1.0]);
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    fmt,
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+    sync::{Condvar, Mutex},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::tstdin::InputLine;
+
+/// A line of a recording, one JSON object per line of the file.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Record {
+    /// milliseconds since the recording started
+    pub t: u64,
+    /// source of the line
+    pub s: String,
+    /// the line was written to the standard error
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub e: bool,
+    /// text of the line
+    pub l: String,
+}
+
+impl Record {
+    pub fn new(line: &InputLine, elapsed: Duration) -> Self {
+        Self {
+            t: elapsed.as_millis() as u64,
+            s: line.source.clone(),
+            e: line.stderr,
+            l: line.text.clone(),
+        }
+    }
+
+    pub fn line(self) -> InputLine {
+        InputLine {
+            stderr: self.e,
+            ..InputLine::new(&self.s, self.l)
+        }
+    }
+
+    /// Time since the recording started
+    pub fn elapsed(&self) -> Duration {
+        Duration::from_millis(self.t)
+    }
+}
+
+/// Writes every input line to a file, with the time it was read at.
+#[derive(Debug)]
+pub struct Recorder {
+    writer: BufWriter<File>,
+    start: Instant,
+}
+
+impl Recorder {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            writer: BufWriter::new(File::create(path)?),
+            start: Instant::now(),
+        })
+    }
+
+    /// Records the line, flushed right away so that nothing is lost if logss is killed.
+    pub fn write(&mut self, line: &InputLine) -> io::Result<()> {
+        let record = Record::new(line, self.start.elapsed());
+        serde_json::to_writer(&mut self.writer, &record)?;
+        self.writer.write_all(b"\n")?;
+        self.writer.flush()
+    }
+}
+
+/// How fast a recording is replayed.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(try_from = "RawSpeed", into = "String")]
+pub enum Speed {
+    /// Times faster than it was recorded
+    Factor(f64),
+    /// One line each time [`Stepper::step`] is called
+    Step,
+}
+
+impl Default for Speed {
+    fn default() -> Self {
+        Self::Factor(1.0)
+    }
+}
+
+impl Speed {
+    /// Time from the start of the replay to a line read `elapsed` into the recording
+    pub fn delay(&self, elapsed: Duration) -> Duration {
+        match self {
+            Self::Factor(factor) => elapsed.div_f64(*factor),
+            Self::Step => Duration::ZERO,
+        }
+    }
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "step" {
+            return Ok(Self::Step);
+        }
+        match s.trim_end_matches('x').parse::<f64>() {
+            Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(Self::Factor(factor)),
+            _ => Err(format!("`{s}` isn't a valid speed")),
+        }
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Factor(factor) => write!(f, "{factor}x"),
+            Self::Step => write!(f, "step"),
+        }
+    }
+}
+
+impl From<Speed> for String {
+    fn from(speed: Speed) -> Self {
+        speed.to_string()
+    }
+}
+
+/// Speed as written in the configuration file, `2`, `2x` or `step`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawSpeed {
+    Number(f64),
+    Text(String),
+}
+
+impl TryFrom<RawSpeed> for Speed {
+    type Error = String;
+
+    fn try_from(raw: RawSpeed) -> Result<Self, Self::Error> {
+        match raw {
+            RawSpeed::Number(factor) => factor.to_string().parse(),
+            RawSpeed::Text(text) => text.parse(),
+        }
+    }
+}
+
+/// Lets a stepped replay go on, one line at a time.
+#[derive(Debug, Default)]
+pub struct Stepper {
+    /// lines that can be replayed
+    steps: Mutex<usize>,
+    changed: Condvar,
+}
+
+impl Stepper {
+    /// Lets one more line be replayed.
+    pub fn step(&self) {
+        *self.steps.lock().unwrap_or_else(|e| e.into_inner()) += 1;
+        self.changed.notify_all();
+    }
+
+    /// Waits until a line can be replayed.
+    pub fn wait(&self) {
+        let mut steps = self.steps.lock().unwrap_or_else(|e| e.into_inner());
+        while *steps == 0 {
+            steps = self.changed.wait(steps).unwrap_or_else(|e| e.into_inner());
+        }
+        *steps -= 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, thread};
+
+    use super::*;
+
+    #[test]
+    fn record() {
+        let path = std::env::temp_dir().join(format!("logss-record-{}", std::process::id()));
+        let mut recorder = Recorder::create(&path).unwrap();
+        recorder.write(&InputLine::from("a \"quoted\"\n")).unwrap();
+        recorder
+            .write(&InputLine::new_stderr("api", "b".to_string()))
+            .unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].ends_with(r#","s":"stdin","l":"a \"quoted\"\n"}"#));
+        assert!(lines[1].ends_with(r#","s":"api","e":true,"l":"b"}"#));
+
+        let record: Record = serde_json::from_str(lines[1]).unwrap();
+        assert!(record.elapsed() < Duration::from_secs(5));
+        assert_eq!(record.line(), InputLine::new_stderr("api", "b".to_string()));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn speed() {
+        assert_eq!("2x".parse(), Ok(Speed::Factor(2.0)));
+        assert_eq!("0.5".parse(), Ok(Speed::Factor(0.5)));
+        assert_eq!("step".parse(), Ok(Speed::Step));
+        for speed in ["0", "-1", "fast", "x"] {
+            assert!(speed.parse::<Speed>().is_err(), "{speed}");
+        }
+        let second = Duration::from_secs(1);
+        assert_eq!(
+            Speed::Factor(10.0).delay(second),
+            Duration::from_millis(100)
+        );
+        assert_eq!(Speed::Step.delay(second), Duration::ZERO);
+        assert_eq!(Speed::Factor(2.0).to_string(), "2x");
+        assert_eq!(
+            serde_yaml::from_str::<Speed>("10").unwrap(),
+            Speed::Factor(10.0)
+        );
+        assert_eq!(serde_yaml::from_str::<Speed>("step").unwrap(), Speed::Step);
+        assert!(serde_yaml::from_str::<Speed>("0").is_err());
+    }
+
+    #[test]
+    fn stepper() {
+        let stepper = Arc::new(Stepper::default());
+        stepper.step();
+        stepper.wait();
+        let waiting = {
+            let stepper = Arc::clone(&stepper);
+            thread::spawn(move || stepper.wait())
+        };
+        thread::sleep(Duration::from_millis(50));
+        assert!(!waiting.is_finished());
+        stepper.step();
+        waiting.join().unwrap();
+    }
+}
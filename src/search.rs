@@ -0,0 +1,252 @@
+use ratatui::{
+    layout::{Position, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+use regex::Regex;
+use unicode_width::UnicodeWidthStr;
+
+/// Style of the search hits.
+const HIT_STYLE: Style = Style::new().fg(Color::Black).bg(Color::Yellow);
+/// Style of the hits on the current line.
+const CURRENT_STYLE: Style = Style::new()
+    .fg(Color::Black)
+    .bg(Color::LightMagenta)
+    .add_modifier(Modifier::BOLD);
+
+/// Search over the lines of a buffer.
+#[derive(Debug, Default, Clone)]
+pub struct Search {
+    pub query: String,
+    /// `None` while the query is empty
+    re: Option<Regex>,
+    /// number of the current hit, counting the lines pushed out of the buffer
+    pub hit: Option<usize>,
+    /// the view has to scroll to the current hit
+    pub jump: bool,
+}
+
+/// Text of a line without its styles.
+pub fn line_text(line: &Line) -> String {
+    line.spans.iter().map(|s| s.content.as_ref()).collect()
+}
+
+impl Search {
+    pub fn push(&mut self, c: char) {
+        self.query.push(c);
+        self.compile();
+    }
+
+    pub fn pop(&mut self) {
+        self.query.pop();
+        self.compile();
+    }
+
+    /// Incomplete regular expressions, as typed, are searched as plain text.
+    fn compile(&mut self) {
+        self.hit = None;
+        self.re = if self.query.is_empty() {
+            None
+        } else {
+            Regex::new(&self.query)
+                .or_else(|_| Regex::new(&regex::escape(&self.query)))
+                .ok()
+        };
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.re.is_some()
+    }
+
+    /// Indexes of the matching lines
+    pub fn hits(&self, lines: &[Line]) -> Vec<usize> {
+        let Some(re) = &self.re else {
+            return vec![];
+        };
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| re.is_match(&line_text(line)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// Numbers of the matching lines, `first` being the number of the first one
+    fn numbered_hits(&self, lines: &[Line], first: usize) -> Vec<usize> {
+        self.hits(lines).into_iter().map(|i| first + i).collect()
+    }
+
+    /// Moves to the next hit below (`forward`) or above the current one, wrapping around.
+    ///
+    /// Without a current hit it moves to the newest one. Hits are numbered from
+    /// `first`, the number of the first line, so they stay put as lines are pushed out.
+    pub fn step(&mut self, lines: &[Line], first: usize, forward: bool) -> Option<usize> {
+        let hits = self.numbered_hits(lines, first);
+        let hit = match (self.hit, forward) {
+            (None, _) => hits.last(),
+            (Some(current), true) => hits.iter().find(|&&h| h > current).or(hits.first()),
+            (Some(current), false) => hits.iter().rev().find(|&&h| h < current).or(hits.last()),
+        };
+        self.hit = hit.copied();
+        self.jump = self.hit.is_some();
+        self.hit
+    }
+
+    /// Position of the current hit among all of them, as `(position, total)`.
+    pub fn position(&self, lines: &[Line], first: usize) -> (usize, usize) {
+        let hits = self.numbered_hits(lines, first);
+        let position = self
+            .hit
+            .and_then(|hit| hits.iter().position(|&h| h == hit))
+            .map_or(0, |p| p + 1);
+        (position, hits.len())
+    }
+
+    /// Highlights the hits of the line, keeping the styles of the rest.
+    pub fn highlight<'a>(&self, line: &Line<'a>, current: bool) -> Line<'a> {
+        let Some(re) = &self.re else {
+            return line.clone();
+        };
+        let text = line_text(line);
+        let ranges: Vec<(usize, usize)> = re
+            .find_iter(&text)
+            .filter(|m| !m.is_empty())
+            .map(|m| (m.start(), m.end()))
+            .collect();
+        if ranges.is_empty() {
+            return line.clone();
+        }
+        let hit_style = if current { CURRENT_STYLE } else { HIT_STYLE };
+        let mut spans = vec![];
+        let mut offset = 0;
+        for span in &line.spans {
+            let content = span.content.as_ref();
+            let end = offset + content.len();
+            // Split the span where the hits start and end
+            let mut cuts = vec![offset, end];
+            for &(s, e) in &ranges {
+                cuts.extend([s, e].into_iter().filter(|&b| b > offset && b < end));
+            }
+            cuts.sort_unstable();
+            cuts.dedup();
+            for cut in cuts.windows(2) {
+                let (a, b) = (cut[0], cut[1]);
+                let style = if ranges.iter().any(|&(s, e)| s <= a && b <= e) {
+                    span.style.patch(hit_style)
+                } else {
+                    span.style
+                };
+                spans.push(Span::styled(
+                    content[a - offset..b - offset].to_string(),
+                    style,
+                ));
+            }
+            offset = end;
+        }
+        let mut ret = line.clone();
+        ret.spans = spans;
+        ret
+    }
+
+    /// Renders the query being typed on the bottom left corner of the frame.
+    pub fn render_prompt(&self, frame: &mut Frame, position: (usize, usize)) {
+        let size = frame.area();
+        if size.height == 0 {
+            return;
+        }
+        let count = match position {
+            (_, 0) if self.is_active() => " [no match]".to_string(),
+            (_, 0) => String::new(),
+            (current, total) => format!(" [{current}/{total}]"),
+        };
+        let prompt = format!("/{}", self.query);
+        let text = format!("{prompt}{count} ");
+        let width = (text.width() as u16).min(size.width);
+        let area = Rect::new(size.x, size.bottom() - 1, width, 1);
+        let paragraph = Paragraph::new(Line::from(Span::styled(
+            text,
+            Style::default().fg(Color::Black).bg(Color::Yellow),
+        )));
+        frame.render_widget(paragraph, area);
+        frame.set_cursor_position(Position::new(
+            (size.x + prompt.width() as u16).min(size.right().saturating_sub(1)),
+            size.bottom() - 1,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn typed(query: &str) -> Search {
+        let mut search = Search::default();
+        for c in query.chars() {
+            search.push(c);
+        }
+        search
+    }
+
+    #[test]
+    fn step() {
+        let lines: Vec<Line> = ["error a", "ok", "error b", "error c", "ok"]
+            .into_iter()
+            .map(Line::from)
+            .collect();
+        let mut search = typed("err");
+        assert_eq!(search.hits(&lines), vec![0, 2, 3]);
+        // The newest hit first
+        assert_eq!(search.step(&lines, 0, true), Some(3));
+        assert!(search.jump);
+        assert_eq!(search.position(&lines, 0), (3, 3));
+        assert_eq!(search.step(&lines, 0, true), Some(0));
+        assert_eq!(search.step(&lines, 0, false), Some(3));
+        assert_eq!(search.step(&lines, 0, false), Some(2));
+        // The first two lines pushed out, the hit stays on "error b"
+        let shifted = &lines[2..];
+        assert_eq!(search.position(shifted, 2), (1, 2));
+        assert_eq!(search.step(shifted, 2, true), Some(3));
+        assert_eq!(search.step(shifted, 2, true), Some(2));
+
+        search.push('x');
+        assert_eq!(search.hit, None);
+        assert_eq!(search.step(&lines, 0, true), None);
+        assert_eq!(search.position(&lines, 0), (0, 0));
+        search.pop();
+        search.pop();
+        search.pop();
+        search.pop();
+        assert!(!search.is_active());
+        assert!(search.hits(&lines).is_empty());
+
+        // Not a valid regex yet
+        let lines = vec![Line::from("call f(x)")];
+        assert_eq!(typed("f(").hits(&lines), vec![0]);
+        assert_eq!(typed("f\\(\\w\\)").hits(&lines), vec![0]);
+    }
+
+    #[test]
+    fn highlight() {
+        let red = Style::default().fg(Color::Red);
+        let line = Line::from(vec![Span::styled("an err", red), Span::raw("or here")]);
+        let search = typed("error");
+        let highlighted = search.highlight(&line, false);
+        assert_eq!(
+            highlighted.spans,
+            vec![
+                Span::styled("an ", red),
+                Span::styled("err", red.patch(HIT_STYLE)),
+                Span::styled("or", HIT_STYLE),
+                Span::raw(" here"),
+            ]
+        );
+        assert_eq!(line_text(&highlighted), "an error here");
+        assert_eq!(
+            search.highlight(&line, true).spans[2],
+            Span::styled("or", CURRENT_STYLE)
+        );
+        assert_eq!(search.highlight(&Line::from("ok"), false), Line::from("ok"));
+    }
+}
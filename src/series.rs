@@ -0,0 +1,201 @@
+use std::{cell::Cell, fmt};
+
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    widgets::Sparkline,
+    Frame,
+};
+
+use crate::cb::CircularBuffer;
+
+/// Height of the sparkline drawn at the bottom of a container.
+pub const SPARKLINE_HEIGHT: u16 = 3;
+/// Resolution of the sparkline bars.
+const SPARKLINE_SCALE: f64 = 1000.0;
+
+/// Numeric values extracted from the matching lines of a container.
+#[derive(Debug)]
+pub struct Series {
+    /// capture group (name or index) or field holding the value
+    pub name: String,
+    values: CircularBuffer<f64>,
+    /// stats of the values, until one is pushed
+    stats: Cell<Option<Stats>>,
+}
+
+/// Summary of the values of a [`Series`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Stats {
+    pub min: f64,
+    pub max: f64,
+    pub avg: f64,
+    pub p95: f64,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "min={} max={} avg={} p95={}",
+            short(self.min),
+            short(self.max),
+            short(self.avg),
+            short(self.p95)
+        )
+    }
+}
+
+/// Formats a number with at most two decimals.
+fn short(n: f64) -> String {
+    let s = format!("{n:.2}");
+    s.trim_end_matches('0').trim_end_matches('.').to_string()
+}
+
+impl Series {
+    pub fn new(name: &str, capacity: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            values: CircularBuffer::new(capacity),
+            stats: Cell::new(None),
+        }
+    }
+
+    pub fn push(&mut self, value: f64) {
+        if value.is_finite() {
+            self.values.push(value);
+            self.stats.set(None);
+        }
+    }
+
+    /// Values from the oldest to the newest
+    pub fn values(&self) -> Vec<f64> {
+        self.values.ordered_clone().buffer
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub fn reset(&mut self) {
+        self.values.reset();
+        self.stats.set(None);
+    }
+
+    /// Returns `None` if there are no values yet.
+    ///
+    /// They are kept from one frame to the next until a value is pushed.
+    pub fn stats(&self) -> Option<Stats> {
+        if self.values.is_empty() {
+            return None;
+        }
+        if let Some(stats) = self.stats.get() {
+            return Some(stats);
+        }
+        let stats = self.compute_stats();
+        self.stats.set(Some(stats));
+        Some(stats)
+    }
+
+    fn compute_stats(&self) -> Stats {
+        let mut sorted = self.values.buffer.clone();
+        sorted.sort_by(f64::total_cmp);
+        let len = sorted.len();
+        // Nearest rank
+        let rank = ((len as f64) * 0.95).ceil() as usize;
+        Stats {
+            min: sorted[0],
+            max: sorted[len - 1],
+            avg: sorted.iter().sum::<f64>() / len as f64,
+            p95: sorted[rank.clamp(1, len) - 1],
+        }
+    }
+
+    /// Draws the newest values that fit in the area, scaled between the minimum and the maximum.
+    pub fn render(&self, frame: &mut Frame, area: Rect, color: Color) {
+        let Some(stats) = self.stats() else {
+            return;
+        };
+        let values = self.values();
+        let start = values.len().saturating_sub(area.width as usize);
+        let range = stats.max - stats.min;
+        let data: Vec<u64> = values[start..]
+            .iter()
+            .map(|v| {
+                if range > 0.0 {
+                    ((v - stats.min) / range * SPARKLINE_SCALE) as u64
+                } else {
+                    // A flat line in the middle
+                    SPARKLINE_SCALE as u64 / 2
+                }
+            })
+            .collect();
+        let sparkline = Sparkline::default()
+            .data(&data)
+            .max(SPARKLINE_SCALE as u64)
+            .style(Style::default().fg(color));
+
+        frame.render_widget(sparkline, area);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use super::*;
+
+    #[test]
+    fn stats() {
+        let mut series = Series::new("took", 100);
+        assert_eq!(series.stats(), None);
+        for v in 1..=100 {
+            series.push(v as f64);
+        }
+        series.push(f64::NAN);
+        assert_eq!(
+            series.stats(),
+            Some(Stats {
+                min: 1.0,
+                max: 100.0,
+                avg: 50.5,
+                p95: 95.0
+            })
+        );
+        // The oldest values are dropped
+        series.push(1000.0);
+        assert_eq!(series.stats().unwrap().min, 2.0);
+        assert_eq!(series.values().last(), Some(&1000.0));
+        series.reset();
+        assert_eq!(series.stats(), None);
+        series.push(7.0);
+        assert_eq!(series.stats().unwrap().max, 7.0);
+
+        let mut series = Series::new("took", 10);
+        series.push(0.25);
+        series.push(1.0 / 3.0);
+        assert_eq!(
+            series.stats().unwrap().to_string(),
+            "min=0.25 max=0.33 avg=0.29 p95=0.33"
+        );
+    }
+
+    #[test]
+    fn render() {
+        let backend = TestBackend::new(4, 1);
+        let mut terminal = Terminal::new(backend).unwrap();
+        let mut series = Series::new("1", 10);
+        for v in [5.0, 0.0, 5.0, 10.0, 0.0] {
+            series.push(v);
+        }
+        terminal
+            .draw(|f| series.render(f, f.area(), Color::Red))
+            .unwrap();
+        // Only the newest values that fit
+        let mut expected = Buffer::with_lines(vec![" ▄█ "]);
+        for x in 0..4 {
+            expected[(x, 0)].set_fg(Color::Red);
+        }
+        terminal.backend().assert_buffer(&expected);
+    }
+}
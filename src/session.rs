@@ -0,0 +1,62 @@
+use std::path::Path;
+
+use anyhow::Result;
+use serde_yaml::Value;
+
+use crate::args::Args;
+
+/// Where the session is saved unless told otherwise.
+pub const SESSION_FILE: &str = "logss-session.yaml";
+
+/// Saves the configuration of a session, to be resumed with `-f`.
+pub fn save(args: &Args, path: &Path) -> Result<()> {
+    let mut value = serde_yaml::to_value(args)?;
+    strip(&mut value);
+    std::fs::write(path, serde_yaml::to_string(&value)?)?;
+    Ok(())
+}
+
+/// Leaves out the settings that are not set, so that the file stays readable.
+fn strip(value: &mut Value) {
+    match value {
+        Value::Mapping(mapping) => {
+            mapping.retain(|_, v| {
+                strip(v);
+                !v.is_null()
+            });
+        }
+        Value::Sequence(sequence) => sequence.iter_mut().for_each(strip),
+        _ => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::args::LocalContainer;
+
+    #[test]
+    fn save_and_load() {
+        let path = std::env::temp_dir().join(format!("logss-session-{}", std::process::id()));
+        let args = Args {
+            containers: vec![LocalContainer {
+                re: "ERROR".to_string(),
+                exclude: Some(vec!["timeout".to_string()]),
+                hide: Some(true),
+                ..Default::default()
+            }],
+            wrap: Some(true),
+            ..Default::default()
+        };
+        save(&args, &path).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        assert!(
+            text.starts_with("containers:\n- re: ERROR\n  exclude:\n  - timeout\n  hide: true\n")
+        );
+        assert!(!text.contains("null"));
+        let loaded: Args = serde_yaml::from_str(&text).unwrap();
+        assert_eq!(loaded.containers, args.containers);
+        assert_eq!(loaded.wrap, Some(true));
+        std::fs::remove_file(&path).unwrap();
+    }
+}
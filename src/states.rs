@@ -1,8 +1,12 @@
+use std::collections::BTreeMap;
+
 use ratatui::{
     layout::Direction,
     style::{Color, Style},
 };
 
+use crate::rates::{Rates, RATE_BUCKET_SIZES};
+
 #[derive(Debug, Eq, PartialEq)]
 pub enum Views {
     RawBuffer,
@@ -25,12 +29,24 @@ pub struct AppState {
     pub paused: bool,
     pub show: Views,
     pub wrap: bool,
+    /// multi-line events are shown whole
+    pub unfold: bool,
     pub help: bool,
     pub barchart: bool,
+    pub rates: bool,
+    pub triggers: bool,
+    /// index of the bucket size of the rate chart
+    pub rate_bucket: usize,
     pub show_input: bool,
+    /// the search query is being typed
+    pub searching: bool,
     pub zoom_id: Option<u8>,
     pub scroll_direction: ScrollDirection,
     pub direction: Direction,
+    /// lines hidden by the global exclude patterns
+    pub excluded: u64,
+    /// Messages shown in the status line, by topic
+    pub status: BTreeMap<String, String>,
 }
 
 impl Default for AppState {
@@ -39,13 +55,20 @@ impl Default for AppState {
             running: false,
             paused: false,
             wrap: false,
+            unfold: false,
             show: Views::Containers,
             direction: Direction::Vertical,
             help: false,
             barchart: false,
+            rates: false,
+            triggers: false,
+            rate_bucket: 0,
             show_input: false,
+            searching: false,
             zoom_id: None,
             scroll_direction: ScrollDirection::NONE,
+            excluded: 0,
+            status: BTreeMap::new(),
         }
     }
 }
@@ -63,6 +86,10 @@ impl AppState {
         self.show_input
     }
 
+    pub const fn searching(&self) -> bool {
+        self.searching
+    }
+
     pub fn stop(&mut self) {
         self.running = false;
     }
@@ -83,6 +110,10 @@ impl AppState {
         self.wrap = !self.wrap;
     }
 
+    pub fn flip_unfold(&mut self) {
+        self.unfold = !self.unfold;
+    }
+
     pub fn flip_help(&mut self) {
         self.help = !self.help;
     }
@@ -91,6 +122,19 @@ impl AppState {
         self.barchart = !self.barchart;
     }
 
+    pub fn flip_rates(&mut self) {
+        self.rates = !self.rates;
+    }
+
+    pub fn flip_triggers(&mut self) {
+        self.triggers = !self.triggers;
+    }
+
+    /// Cycles through the bucket sizes of the rate chart
+    pub fn next_rate_bucket(&mut self) {
+        self.rate_bucket = (self.rate_bucket + 1) % RATE_BUCKET_SIZES.len();
+    }
+
     pub fn flip_show_input(&mut self) {
         self.show_input = !self.show_input;
     }
@@ -105,6 +149,14 @@ impl AppState {
         self.scroll_direction = ScrollDirection::DOWN;
     }
 
+    pub fn set_status(&mut self, topic: &str, message: String) {
+        self.status.insert(topic.to_string(), message);
+    }
+
+    pub fn clear_status(&mut self, topic: &str) {
+        self.status.remove(topic);
+    }
+
     pub fn flip_direction(&mut self) {
         if self.direction == Direction::Vertical {
             self.direction = Direction::Horizontal;
@@ -119,8 +171,18 @@ pub struct ContainerState {
     pub paused: bool,
     pub hide: bool,
     pub wrap: bool,
+    /// multi-line events are shown whole
+    pub unfold: bool,
+    /// auto-scroll stopped at a search hit while the lines keep coming in
+    pub scroll_lock: bool,
     pub scroll: u16,
     pub count: u64,
+    /// matching lines hidden by an exclude pattern
+    pub excluded: u64,
+    /// matches per second, to chart the rate
+    pub rates: Rates,
+    /// triggers that failed, timed out or could not start
+    pub trigger_failures: u64,
     pub color: Color,
     pub style: Style,
 }
@@ -131,8 +193,13 @@ impl Default for ContainerState {
             paused: false,
             hide: false,
             wrap: false,
+            unfold: false,
+            scroll_lock: false,
             scroll: 0,
             count: 0,
+            excluded: 0,
+            rates: Rates::default(),
+            trigger_failures: 0,
             color: Color::Red,
             style: Style::default().fg(Color::White).bg(Color::Black),
         }
@@ -157,12 +224,40 @@ mod tests {
     fn test_app_state() {
         let appstate = AppState::default();
         assert!(!appstate.wrap);
+        assert!(!appstate.unfold);
         assert!(!appstate.paused);
         assert!(!appstate.running);
         assert_eq!(appstate.show, Views::Containers);
         assert_eq!(appstate.direction, Direction::Vertical);
         assert!(!appstate.help);
         assert!(!appstate.show_input);
+        assert!(!appstate.searching);
         assert_eq!(appstate.zoom_id, None);
+        assert!(appstate.status.is_empty());
+        assert!(!appstate.rates);
+    }
+
+    #[test]
+    fn rate_bucket() {
+        let mut appstate = AppState::default();
+        appstate.flip_rates();
+        assert!(appstate.rates);
+        for _ in 0..RATE_BUCKET_SIZES.len() - 1 {
+            appstate.next_rate_bucket();
+        }
+        assert_eq!(appstate.rate_bucket, RATE_BUCKET_SIZES.len() - 1);
+        appstate.next_rate_bucket();
+        assert_eq!(appstate.rate_bucket, 0);
+    }
+
+    #[test]
+    fn status() {
+        let mut appstate = AppState::default();
+        appstate.set_status("api", "exited".to_string());
+        appstate.set_status("api", "exited with code 1".to_string());
+        assert_eq!(appstate.status.len(), 1);
+        assert_eq!(appstate.status["api"], "exited with code 1");
+        appstate.clear_status("api");
+        assert!(appstate.status.is_empty());
     }
 }
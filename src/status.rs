@@ -0,0 +1,63 @@
+use ratatui::{
+    layout::Rect,
+    style::{Color, Style},
+    text::{Line, Span},
+    widgets::Paragraph,
+    Frame,
+};
+use unicode_width::UnicodeWidthStr;
+
+/// Renders a one line status on the bottom right corner of the frame.
+///
+/// Nothing is rendered if there are no items.
+pub fn render_status(frame: &mut Frame, items: &[String]) {
+    if items.is_empty() {
+        return;
+    }
+    let text = format!(" {} ", items.join(" | "));
+    let size = frame.area();
+    if size.height == 0 {
+        return;
+    }
+    let width = (text.width() as u16).min(size.width);
+    let area = Rect::new(size.right() - width, size.bottom() - 1, width, 1);
+    let paragraph = Paragraph::new(Line::from(Span::styled(
+        text,
+        Style::default().fg(Color::Black).bg(Color::Yellow),
+    )));
+
+    frame.render_widget(paragraph, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use ratatui::{backend::TestBackend, buffer::Buffer, Terminal};
+
+    use super::*;
+
+    #[test]
+    fn test_render_status() {
+        let backend = TestBackend::new(16, 2);
+        let mut terminal = Terminal::new(backend).unwrap();
+        terminal
+            .draw(|f| {
+                render_status(f, &[]);
+            })
+            .unwrap();
+        terminal
+            .backend()
+            .assert_buffer(&Buffer::with_lines(vec!["                "; 2]));
+
+        terminal
+            .draw(|f| {
+                render_status(f, &["a: 1".to_string(), "b".to_string()]);
+            })
+            .unwrap();
+        let mut expected = Buffer::with_lines(vec!["                ", "       a: 1 | b "]);
+        for x in 6..=15 {
+            expected[(x, 1)].set_fg(Color::Black);
+            expected[(x, 1)].set_bg(Color::Yellow);
+        }
+        terminal.backend().assert_buffer(&expected);
+    }
+}
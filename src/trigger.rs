@@ -0,0 +1,455 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    io::{self, Read, Write},
+    process::{Child, ChildStderr, ChildStdout, Command, Stdio},
+    str::FromStr,
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use ratatui::{
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    Frame,
+};
+use serde::{Deserialize, Serialize};
+use wait_timeout::ChildExt;
+
+use crate::{popup::render_popup, queue::QueueStats};
+
+/// Placeholder of the matching line in the trigger command.
+pub const LINE_PLACEHOLDER: &str = "__line__";
+/// Environment variable holding the matching line.
+pub const LINE_VAR: &str = "LOGSS_LINE";
+/// Environment variable holding the pattern of the container.
+pub const CONTAINER_VAR: &str = "LOGSS_CONTAINER";
+/// Environment variable holding the number of events merged into the one of the trigger.
+pub const COALESCED_VAR: &str = "LOGSS_COALESCED";
+/// Prefix of the environment variables holding the capture groups.
+pub const CAPTURE_VAR_PREFIX: &str = "LOGSS_CAP_";
+/// Bytes of the output of a trigger that are kept.
+pub const OUTPUT_EXCERPT: usize = 512;
+/// How long the output is waited for once the trigger ended.
+///
+/// Processes started by the trigger may keep its output open.
+const OUTPUT_GRACE: Duration = Duration::from_millis(100);
+/// Number of trigger outcomes kept in the history.
+pub const TRIGGER_HISTORY: usize = 100;
+
+/// How the matching line is handed to the trigger command.
+///
+/// The line and the capture groups are always available as environment
+/// variables (`LOGSS_LINE`, `LOGSS_CONTAINER`, `LOGSS_COALESCED` and `LOGSS_CAP_<name>`).
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum TriggerInput {
+    /// `__line__` expands to the line without the shell interpreting it
+    #[default]
+    Safe,
+    /// `__line__` is replaced by the line as is, the shell interprets it
+    Raw,
+    /// Like `safe`, and the line is also written to the standard input
+    Stdin,
+}
+
+impl FromStr for TriggerInput {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "safe" => Ok(Self::Safe),
+            "raw" => Ok(Self::Raw),
+            "stdin" => Ok(Self::Stdin),
+            _ => Err(format!("`{s}` isn't a valid trigger input")),
+        }
+    }
+}
+
+/// What a trigger is run for.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct TriggerEvent {
+    /// matching line, without its line break
+    pub line: String,
+    /// pattern of the container
+    pub container: String,
+    /// capture groups by name, or by index if they have none
+    pub captures: Vec<(String, String)>,
+    /// earlier events merged into this one when the queue was full
+    pub coalesced: usize,
+}
+
+impl TriggerEvent {
+    pub fn new(line: &str, container: &str) -> Self {
+        Self {
+            line: line.trim_end_matches(['\n', '\r']).to_string(),
+            container: container.to_string(),
+            captures: vec![],
+            coalesced: 0,
+        }
+    }
+
+    fn env(&self) -> Vec<(String, String)> {
+        let mut env = vec![
+            (LINE_VAR.to_string(), self.line.clone()),
+            (CONTAINER_VAR.to_string(), self.container.clone()),
+            (COALESCED_VAR.to_string(), self.coalesced.to_string()),
+        ];
+        for (name, value) in &self.captures {
+            env.push((format!("{CAPTURE_VAR_PREFIX}{name}"), value.clone()));
+        }
+        env
+    }
+}
+
+/// How a trigger ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TriggerStatus {
+    /// exit code, `None` if killed by a signal
+    Exited(Option<i32>),
+    /// killed after its timeout
+    TimedOut,
+    /// built-in action delivered
+    Done,
+    /// could not be started or waited for
+    Failed(String),
+}
+
+impl fmt::Display for TriggerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Exited(Some(code)) => write!(f, "exit {code}"),
+            Self::Exited(None) => write!(f, "killed by a signal"),
+            Self::TimedOut => write!(f, "timed out"),
+            Self::Done => write!(f, "done"),
+            Self::Failed(e) => write!(f, "failed: {e}"),
+        }
+    }
+}
+
+/// Result of running a trigger.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TriggerOutcome {
+    /// id of the container
+    pub id: u8,
+    pub event: TriggerEvent,
+    pub status: TriggerStatus,
+    /// beginning of the standard output
+    pub stdout: String,
+    /// beginning of the standard error
+    pub stderr: String,
+    pub finished: Instant,
+}
+
+impl TriggerOutcome {
+    pub fn new(id: u8, event: TriggerEvent, status: TriggerStatus) -> Self {
+        Self {
+            id,
+            event,
+            status,
+            stdout: String::new(),
+            stderr: String::new(),
+            finished: Instant::now(),
+        }
+    }
+
+    pub fn failed(&self) -> bool {
+        !matches!(
+            self.status,
+            TriggerStatus::Exited(Some(0)) | TriggerStatus::Done
+        )
+    }
+}
+
+/// Builds the `sh -c` command of a trigger.
+pub fn command(template: &str, input: TriggerInput, event: &TriggerEvent) -> Command {
+    let cmd = match input {
+        TriggerInput::Raw => template.replace(LINE_PLACEHOLDER, &event.line),
+        TriggerInput::Safe | TriggerInput::Stdin => substitute_var(template, LINE_VAR),
+    };
+    let mut command = Command::new("sh");
+    command
+        .arg("-c")
+        .arg(cmd)
+        .envs(event.env())
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped());
+    if input == TriggerInput::Stdin {
+        command.stdin(Stdio::piped());
+    }
+    command
+}
+
+/// Spawns the trigger command, [`wait`] feeds it and collects its outcome.
+pub fn spawn(template: &str, input: TriggerInput, event: &TriggerEvent) -> io::Result<Child> {
+    command(template, input, event).spawn()
+}
+
+/// Reads the beginning of the output and discards the rest, so the child never blocks on it.
+fn read_excerpt<R: Read + Send + 'static>(reader: Option<R>) -> mpsc::Receiver<String> {
+    let (sender, receiver) = mpsc::channel();
+    if let Some(mut reader) = reader {
+        thread::spawn(move || {
+            let mut excerpt = vec![];
+            let _ = reader
+                .by_ref()
+                .take(OUTPUT_EXCERPT as u64)
+                .read_to_end(&mut excerpt);
+            let _ = io::copy(&mut reader, &mut io::sink());
+            let _ = sender.send(String::from_utf8_lossy(&excerpt).trim_end().to_string());
+        });
+    }
+    receiver
+}
+
+/// Feeds the line to the trigger and waits for it, killing it after `timeout`.
+pub fn wait(mut child: Child, id: u8, event: TriggerEvent, timeout: Duration) -> TriggerOutcome {
+    let stdout = read_excerpt::<ChildStdout>(child.stdout.take());
+    let stderr = read_excerpt::<ChildStderr>(child.stderr.take());
+    if let Some(mut stdin) = child.stdin.take() {
+        let line = event.line.clone();
+        // A trigger that does not read its input is not an error
+        thread::spawn(move || writeln!(stdin, "{line}"));
+    }
+    let status = match child.wait_timeout(timeout) {
+        Ok(Some(status)) => TriggerStatus::Exited(status.code()),
+        Ok(None) => {
+            let _ = child.kill();
+            let _ = child.wait();
+            TriggerStatus::TimedOut
+        }
+        Err(e) => TriggerStatus::Failed(e.to_string()),
+    };
+    let mut outcome = TriggerOutcome::new(id, event, status);
+    outcome.stdout = stdout.recv_timeout(OUTPUT_GRACE).unwrap_or_default();
+    outcome.stderr = stderr.recv_timeout(OUTPUT_GRACE).unwrap_or_default();
+    outcome
+}
+
+/// Outcomes of the triggers of every container, newest last.
+#[derive(Debug)]
+pub struct TriggerHistory {
+    sender: mpsc::Sender<TriggerOutcome>,
+    receiver: mpsc::Receiver<TriggerOutcome>,
+    pub outcomes: VecDeque<TriggerOutcome>,
+}
+
+impl Default for TriggerHistory {
+    fn default() -> Self {
+        let (sender, receiver) = mpsc::channel();
+        Self {
+            sender,
+            receiver,
+            outcomes: VecDeque::new(),
+        }
+    }
+}
+
+impl TriggerHistory {
+    /// Where the containers send the outcome of their triggers
+    pub fn sender(&self) -> mpsc::Sender<TriggerOutcome> {
+        self.sender.clone()
+    }
+
+    /// Moves the finished triggers to the history and returns them.
+    pub fn collect(&mut self) -> Vec<TriggerOutcome> {
+        let received: Vec<TriggerOutcome> = self.receiver.try_iter().collect();
+        for outcome in &received {
+            if self.outcomes.len() == TRIGGER_HISTORY {
+                self.outcomes.pop_front();
+            }
+            self.outcomes.push_back(outcome.clone());
+        }
+        received
+    }
+
+    /// Renders the history as a popup, newest first, below the stats of the queues.
+    pub fn render(&self, frame: &mut Frame, queues: &QueueStats) {
+        let now = Instant::now();
+        let dim = Style::default().fg(Color::DarkGray);
+        let mut text = vec![Line::from(Span::styled(format!("Queue: {queues}"), dim))];
+        for outcome in self.outcomes.iter().rev() {
+            let status_style = if outcome.failed() {
+                Style::default().fg(Color::LightRed)
+            } else {
+                Style::default().fg(Color::Green)
+            };
+            let ago = now.saturating_duration_since(outcome.finished).as_secs();
+            text.push(Line::from(vec![
+                Span::styled(format!("{ago:>4}s ago [{}] ", outcome.id), dim),
+                Span::styled(
+                    outcome.status.to_string(),
+                    status_style.add_modifier(Modifier::BOLD),
+                ),
+                Span::from(format!(" {}", outcome.event.line)),
+            ]));
+            for (name, output) in [("out", &outcome.stdout), ("err", &outcome.stderr)] {
+                if let Some(first) = output.lines().next() {
+                    text.push(Line::from(Span::styled(
+                        format!("          {name}: {first}"),
+                        dim,
+                    )));
+                }
+            }
+        }
+        if self.outcomes.is_empty() {
+            text.push(Line::from("No trigger has run yet"));
+        }
+        render_popup(frame, "Triggers", &text, (80, 60));
+    }
+}
+
+/// Replaces every `__line__` by an expansion of `var` that the shell does not split
+/// or interpret, whether the placeholder is quoted or not.
+pub fn substitute_var(template: &str, var: &str) -> String {
+    #[derive(PartialEq)]
+    enum Quote {
+        None,
+        Single,
+        Double,
+    }
+
+    let mut ret = String::new();
+    let mut quote = Quote::None;
+    let mut rest = template;
+    while let Some(c) = rest.chars().next() {
+        if rest.starts_with(LINE_PLACEHOLDER) {
+            ret.push_str(&match quote {
+                Quote::None => format!("\"${var}\""),
+                Quote::Double => format!("${var}"),
+                // Close the single quotes around it
+                Quote::Single => format!("'\"${var}\"'"),
+            });
+            rest = &rest[LINE_PLACEHOLDER.len()..];
+            continue;
+        }
+        rest = &rest[c.len_utf8()..];
+        ret.push(c);
+        match (c, &quote) {
+            ('\\', Quote::None | Quote::Double) => {
+                // The escaped character is kept as is
+                if let Some(next) = rest.chars().next() {
+                    ret.push(next);
+                    rest = &rest[next.len_utf8()..];
+                }
+            }
+            ('\'', Quote::None) => quote = Quote::Single,
+            ('\'', Quote::Single) => quote = Quote::None,
+            ('"', Quote::None) => quote = Quote::Double,
+            ('"', Quote::Double) => quote = Quote::None,
+            _ => (),
+        }
+    }
+    ret
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run(template: &str, input: TriggerInput, line: &str) -> String {
+        let mut event = TriggerEvent::new(line, "err(?P<code>\\d+)");
+        event.captures.push(("code".to_string(), "42".to_string()));
+        let child = spawn(template, input, &event).unwrap();
+        let outcome = wait(child, 1, event, Duration::from_secs(5));
+        assert_eq!(outcome.status, TriggerStatus::Exited(Some(0)));
+        outcome.stdout + "\n"
+    }
+
+    #[test]
+    fn outcomes() {
+        let event = TriggerEvent::new("line", "re");
+        let child = spawn("echo out; echo err >&2; exit 3", TriggerInput::Safe, &event).unwrap();
+        let outcome = wait(child, 2, event.clone(), Duration::from_secs(5));
+        assert_eq!(outcome.id, 2);
+        assert_eq!(outcome.status, TriggerStatus::Exited(Some(3)));
+        assert_eq!(outcome.stdout, "out");
+        assert_eq!(outcome.stderr, "err");
+        assert!(outcome.failed());
+
+        // Its child keeps the output open
+        let child = spawn("sleep 5; true", TriggerInput::Safe, &event).unwrap();
+        let started = Instant::now();
+        let outcome = wait(child, 2, event.clone(), Duration::from_millis(100));
+        assert!(started.elapsed() < Duration::from_secs(1));
+        assert_eq!(outcome.status, TriggerStatus::TimedOut);
+        assert_eq!(outcome.status.to_string(), "timed out");
+
+        // Large outputs are cut and do not block the child
+        let child = spawn("seq 100000", TriggerInput::Safe, &event).unwrap();
+        let outcome = wait(child, 2, event.clone(), Duration::from_secs(5));
+        assert_eq!(outcome.status, TriggerStatus::Exited(Some(0)));
+        assert!(outcome.stdout.len() <= OUTPUT_EXCERPT);
+        assert!(outcome.stdout.starts_with("1\n2\n"));
+    }
+
+    #[test]
+    fn history() {
+        let mut history = TriggerHistory::default();
+        let sender = history.sender();
+        for i in 0..TRIGGER_HISTORY + 1 {
+            let event = TriggerEvent::new(&i.to_string(), "re");
+            sender
+                .send(TriggerOutcome::new(
+                    1,
+                    event,
+                    TriggerStatus::Exited(Some(0)),
+                ))
+                .unwrap();
+        }
+        assert_eq!(history.collect().len(), TRIGGER_HISTORY + 1);
+        assert_eq!(history.outcomes.len(), TRIGGER_HISTORY);
+        assert_eq!(history.outcomes[0].event.line, "1");
+        assert!(history.collect().is_empty());
+    }
+
+    #[test]
+    fn substitute() {
+        let var = LINE_VAR;
+        assert_eq!(substitute_var("echo __line__", var), "echo \"$LOGSS_LINE\"");
+        assert_eq!(
+            substitute_var("echo '__line__' \"x __line__\"", var),
+            "echo ''\"$LOGSS_LINE\"'' \"x $LOGSS_LINE\""
+        );
+        assert_eq!(
+            substitute_var("echo \\'__line__ \"\\\"__line__\"", var),
+            "echo \\'\"$LOGSS_LINE\" \"\\\"$LOGSS_LINE\""
+        );
+        assert_eq!(substitute_var("echo ñ__line", var), "echo ñ__line");
+    }
+
+    #[test]
+    fn safe_substitution() {
+        let line = "a 'b' \"c\"; $(echo d) `echo e` $HOME\n";
+        let expected = "a 'b' \"c\"; $(echo d) `echo e` $HOME\n";
+        for template in ["echo __line__", "echo '__line__'", "echo \"__line__\""] {
+            assert_eq!(run(template, TriggerInput::Safe, line), expected);
+        }
+        assert_eq!(
+            run("echo 'x __line__ y'", TriggerInput::Safe, "a;b"),
+            "x a;b y\n"
+        );
+        // The shell interprets it
+        assert_eq!(
+            run("echo __line__", TriggerInput::Raw, "a; echo b"),
+            "a\nb\n"
+        );
+    }
+
+    #[test]
+    fn env_and_stdin() {
+        assert_eq!(
+            run(
+                "echo \"$LOGSS_CONTAINER $LOGSS_CAP_code $LOGSS_LINE\"",
+                TriggerInput::Safe,
+                "err42"
+            ),
+            "err(?P<code>\\d+) 42 err42\n"
+        );
+        assert_eq!(run("cat", TriggerInput::Stdin, "a $(b)\n"), "a $(b)\n");
+        assert_eq!("stdin".parse(), Ok(TriggerInput::Stdin));
+        assert!("shell".parse::<TriggerInput>().is_err());
+    }
+}
@@ -1,16 +1,155 @@
 use std::{
-    io::{stdin, BufRead, BufReader, Error},
-    process::{Command, Stdio},
-    sync::{mpsc, mpsc::Sender},
+    fs::{self, File},
+    io::{self, stdin, BufRead, BufReader, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    process::{Child, Command, Stdio},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc, Arc, Mutex,
+    },
     thread,
+    time::{Duration, Instant},
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use serde_json::Value;
+
+use crate::{
+    args::{LocalSource, Restart},
+    replay::{Record, Recorder, Speed, Stepper},
+};
+
+/// Name given to lines read from the standard input.
+pub const STDIN_SOURCE: &str = "stdin";
+
+/// How often a followed file is checked for new data.
+const FOLLOW_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Delay before the first restart of a command, doubled on each restart.
+const RESTART_BACKOFF_MIN: Duration = Duration::from_secs(1);
+
+/// Longest delay between restarts of a command.
+const RESTART_BACKOFF_MAX: Duration = Duration::from_secs(60);
+
+/// A command that runs for this long is considered healthy and the backoff is reset.
+const RESTART_BACKOFF_RESET: Duration = Duration::from_secs(60);
+
+/// A line read from one of the inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputLine {
+    /// Name of the source that produced the line
+    pub source: String,
+    pub text: String,
+    /// The line was written to the standard error of a command
+    pub stderr: bool,
+    /// Fields of structured lines, boxed to keep the events small
+    pub fields: Option<Box<Value>>,
+}
+
+impl InputLine {
+    pub fn new(source: &str, text: String) -> Self {
+        Self {
+            source: source.to_string(),
+            text,
+            stderr: false,
+            fields: None,
+        }
+    }
+
+    pub fn new_stderr(source: &str, text: String) -> Self {
+        Self {
+            stderr: true,
+            ..Self::new(source, text)
+        }
+    }
+}
+
+/// Something that happened on one of the inputs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SourceEvent {
+    /// A line was read
+    Line(InputLine),
+    /// The command of a source exited, `status` is `None` if it was killed by a signal
+    Exited {
+        source: String,
+        status: Option<i32>,
+        /// Time left before the command is restarted
+        restart_in: Option<Duration>,
+    },
+    /// The command of a source could not be started again
+    Failed {
+        source: String,
+        error: String,
+        /// Time left before the next attempt
+        restart_in: Option<Duration>,
+    },
+    /// The command of a source was restarted
+    Restarted { source: String, attempt: u32 },
+}
+
+impl From<InputLine> for SourceEvent {
+    fn from(line: InputLine) -> Self {
+        Self::Line(line)
+    }
+}
+
+impl From<String> for SourceEvent {
+    fn from(text: String) -> Self {
+        Self::Line(text.into())
+    }
+}
+
+impl From<&str> for SourceEvent {
+    fn from(text: &str) -> Self {
+        Self::Line(text.into())
+    }
+}
+
+impl From<String> for InputLine {
+    fn from(text: String) -> Self {
+        Self::new(STDIN_SOURCE, text)
+    }
+}
+
+impl From<&str> for InputLine {
+    fn from(text: &str) -> Self {
+        Self::new(STDIN_SOURCE, text.to_string())
+    }
+}
+
+/// Sending half of the input channel.
+///
+/// It keeps count of the events that were sent but not yet received so that the
+/// backlog can be reported, and records the lines when asked to.
+#[derive(Debug, Clone)]
+pub struct LineSender {
+    inner: mpsc::Sender<SourceEvent>,
+    pending: Arc<AtomicUsize>,
+    recorder: Option<Arc<Mutex<Recorder>>>,
+}
+
+impl LineSender {
+    pub fn send<E: Into<SourceEvent>>(&self, event: E) -> Result<(), mpsc::SendError<SourceEvent>> {
+        let event = event.into();
+        if let (Some(recorder), SourceEvent::Line(line)) = (&self.recorder, &event) {
+            let mut recorder = recorder.lock().unwrap_or_else(|e| e.into_inner());
+            recorder.write(line).ok();
+        }
+        self.pending.fetch_add(1, Ordering::Relaxed);
+        self.inner.send(event).map_err(|e| {
+            self.pending.fetch_sub(1, Ordering::Relaxed);
+            e
+        })
+    }
+}
 
 #[derive(Debug)]
 pub struct StdinHandler {
-    receiver: mpsc::Receiver<String>,
-    pub sender: mpsc::Sender<String>,
+    receiver: mpsc::Receiver<SourceEvent>,
+    pub sender: LineSender,
+    pending: Arc<AtomicUsize>,
+    /// lets a stepped replay go on
+    stepper: Arc<Stepper>,
 }
 
 impl Default for StdinHandler {
@@ -22,65 +161,798 @@ impl Default for StdinHandler {
 impl StdinHandler {
     pub fn new() -> Self {
         let (sender, receiver) = mpsc::channel();
-        Self { receiver, sender }
+        let pending = Arc::new(AtomicUsize::new(0));
+        let sender = LineSender {
+            inner: sender,
+            pending: pending.clone(),
+            recorder: None,
+        };
+        Self {
+            receiver,
+            sender,
+            pending,
+            stepper: Arc::default(),
+        }
     }
 
-    pub fn init(&self, cmd: Option<Vec<String>>) -> Result<()> {
-        let sender = self.sender.clone();
-        match cmd {
-            Some(inner_cmd) => {
-                let child = Command::new(&inner_cmd[0])
-                    .args(&inner_cmd[1..])
-                    .stderr(Stdio::null())
-                    .stdout(Stdio::piped())
-                    .spawn()?;
+    /// Records every line read from now on, see [`Recorder`].
+    ///
+    /// It has to be called before the sources are started.
+    pub fn record(&mut self, path: &Path) -> Result<()> {
+        let recorder = Recorder::create(path)
+            .map_err(|e| anyhow!("Failed to record to '{}': {e}", path.display()))?;
+        self.sender.recorder = Some(Arc::new(Mutex::new(recorder)));
+        Ok(())
+    }
 
-                let stdout = child
-                    .stdout
-                    .ok_or_else(|| Error::other("Failed to run command"))?;
-                let reader = BufReader::new(stdout);
-                read_lines_and_send(reader, sender);
-            }
-            // If no command set then we are being pipped
-            None => {
-                let stdin = stdin();
-                let reader = BufReader::new(stdin);
+    /// Replays a recording instead of reading the sources.
+    pub fn replay(&self, path: &Path, speed: Speed) -> Result<()> {
+        let file =
+            File::open(path).map_err(|e| anyhow!("Failed to replay '{}': {e}", path.display()))?;
+        replay_file(file, speed, self.sender.clone(), Arc::clone(&self.stepper));
+        Ok(())
+    }
+
+    /// Replays one more line of a stepped replay.
+    pub fn step(&self) {
+        self.stepper.step();
+    }
+
+    /// Starts a reader thread for each source.
+    ///
+    /// With no sources at all the standard input is read.
+    pub fn init(&self, sources: &[LocalSource]) -> Result<()> {
+        // If no sources set then we are being pipped
+        if sources.is_empty() {
+            let reader = BufReader::new(stdin());
+            read_lines_and_send(reader, STDIN_SOURCE, self.sender.clone());
+        }
+        for source in sources {
+            self.init_source(source)?;
+        }
+
+        Ok(())
+    }
 
-                read_lines_and_send(reader, sender);
+    fn init_source(&self, source: &LocalSource) -> Result<()> {
+        let name = source.name.as_deref().unwrap_or(STDIN_SOURCE);
+        let sender = self.sender.clone();
+        if let Some(path) = &source.follow {
+            let start = if source.from_start.unwrap_or_default() {
+                StartAt::Beginning
+            } else {
+                match source.lines {
+                    None | Some(0) => StartAt::End,
+                    Some(lines) => StartAt::Lines(lines),
+                }
+            };
+            follow_file(PathBuf::from(path), start, name, sender);
+            return Ok(());
+        }
+        match source.command.as_slice() {
+            [] => return Err(anyhow!("Empty command for source '{name}'")),
+            [cmd] if cmd == "-" => {
+                let reader = BufReader::new(stdin());
+                read_lines_and_send(reader, name, sender);
+            }
+            [cmd, args @ ..] => {
+                let restart = source.restart.unwrap_or_default();
+                run_command(cmd, args, name, restart, sender)?;
             }
         }
 
         Ok(())
     }
 
-    pub fn recv(&self) -> Result<String, mpsc::RecvError> {
-        self.receiver.recv()
+    pub fn recv(&self) -> Result<SourceEvent, mpsc::RecvError> {
+        let event = self.receiver.recv()?;
+        self.pending.fetch_sub(1, Ordering::Relaxed);
+        Ok(event)
+    }
+
+    pub fn try_recv(&self) -> Result<SourceEvent, mpsc::TryRecvError> {
+        let event = self.receiver.try_recv()?;
+        self.pending.fetch_sub(1, Ordering::Relaxed);
+        Ok(event)
+    }
+
+    /// Returns the number of events waiting to be received
+    pub fn pending(&self) -> usize {
+        self.pending.load(Ordering::Relaxed)
     }
+}
+
+/// Runs a command and sends both its standard output and error.
+///
+/// Once its output is closed the command is waited for and its exit status sent.
+/// Depending on `restart` it is then started again, waiting longer after each attempt.
+fn run_command(
+    cmd: &str,
+    args: &[String],
+    source: &str,
+    restart: Restart,
+    sender: LineSender,
+) -> Result<()> {
+    let mut command = Command::new(cmd);
+    command
+        .args(args)
+        .stdin(Stdio::null())
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped());
+    // Failing to start the first time is reported right away
+    let mut child = Some(command.spawn()?);
+    let source = source.to_string();
 
-    pub fn try_recv(&self) -> Result<String, mpsc::TryRecvError> {
-        self.receiver.try_recv()
+    thread::spawn(move || {
+        let mut backoff = RESTART_BACKOFF_MIN;
+        let mut attempt = 0;
+        let mut error = None;
+        loop {
+            let started = Instant::now();
+            let status = match child.take() {
+                Some(child) => wait_command(child, &source, &sender),
+                None => None,
+            };
+            let restart_in = match restart {
+                Restart::Always => true,
+                Restart::OnFailure => status != Some(0),
+                Restart::Never => false,
+            };
+            if started.elapsed() >= RESTART_BACKOFF_RESET {
+                backoff = RESTART_BACKOFF_MIN;
+            }
+            let restart_in = restart_in.then_some(backoff);
+            let exited = match error.take() {
+                Some(error) => SourceEvent::Failed {
+                    source: source.clone(),
+                    error,
+                    restart_in,
+                },
+                None => SourceEvent::Exited {
+                    source: source.clone(),
+                    status,
+                    restart_in,
+                },
+            };
+            if sender.send(exited).is_err() || restart_in.is_none() {
+                return;
+            }
+
+            thread::sleep(backoff);
+            backoff = (backoff * 2).min(RESTART_BACKOFF_MAX);
+            attempt += 1;
+            child = match command.spawn() {
+                Ok(child) => Some(child),
+                Err(e) => {
+                    error = Some(e.to_string());
+                    None
+                }
+            };
+            let restarted = SourceEvent::Restarted {
+                source: source.clone(),
+                attempt,
+            };
+            if child.is_some() && sender.send(restarted).is_err() {
+                return;
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Sends the output of a running command and returns its exit status once it is done.
+fn wait_command(mut child: Child, source: &str, sender: &LineSender) -> Option<i32> {
+    let stdout = child.stdout.take();
+    let stderr_reader = child.stderr.take().map(|stderr| {
+        let (source, sender) = (source.to_string(), sender.clone());
+        thread::spawn(move || {
+            read_lines(
+                BufReader::new(stderr),
+                |text| InputLine::new_stderr(&source, text),
+                &sender,
+            );
+        })
+    });
+    if let Some(stdout) = stdout {
+        read_lines(
+            BufReader::new(stdout),
+            |text| InputLine::new(source, text),
+            sender,
+        );
     }
+    if let Some(stderr_reader) = stderr_reader {
+        stderr_reader.join().ok();
+    }
+    child.wait().ok().and_then(|s| s.code())
 }
 
-fn read_lines_and_send<R>(mut reader: R, sender: Sender<String>)
+fn read_lines_and_send<R>(reader: R, source: &str, sender: LineSender)
 where
     R: BufRead + Send + 'static,
+{
+    let source = source.to_string();
+    thread::spawn(move || read_lines(reader, |text| InputLine::new(&source, text), &sender));
+}
+
+/// Sends every line of `reader` until it is closed.
+fn read_lines<R, F>(mut reader: R, to_line: F, sender: &LineSender)
+where
+    R: BufRead,
+    F: Fn(String) -> InputLine,
 {
     let mut line = String::new();
-    thread::spawn(move || loop {
+    loop {
         match reader.read_line(&mut line) {
-            Ok(len) => {
-                if len == 0 {
+            Ok(0) => break,
+            Ok(_) => {
+                if sender.send(to_line(line.clone())).is_err() {
                     break;
-                } else {
-                    sender.send(line.clone()).ok();
                 }
             }
             Err(e) => {
-                sender.send(e.to_string()).ok();
+                sender.send(to_line(e.to_string())).ok();
                 break;
             }
         }
         line.clear();
+    }
+}
+
+/// Sends the lines of a recording as they were read, faster or one at a time.
+fn replay_file(file: File, speed: Speed, sender: LineSender, stepper: Arc<Stepper>) {
+    thread::spawn(move || {
+        let start = Instant::now();
+        for text in BufReader::new(file).lines() {
+            let Ok(text) = text else {
+                break;
+            };
+            // Lines cut short when the recording stopped are skipped
+            let Ok(record) = serde_json::from_str::<Record>(&text) else {
+                continue;
+            };
+            match speed {
+                Speed::Step => stepper.wait(),
+                Speed::Factor(_) => {
+                    let at = start + speed.delay(record.elapsed());
+                    thread::sleep(at.saturating_duration_since(Instant::now()));
+                }
+            }
+            if sender.send(record.line()).is_err() {
+                return;
+            }
+        }
+    });
+}
+
+/// Where to start reading a followed file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum StartAt {
+    Beginning,
+    End,
+    /// The last N lines
+    Lines(usize),
+}
+
+/// Follows a file like `tail -F` does.
+///
+/// Truncation restarts the reading from the beginning of the file, while rotation
+/// (the path now pointing to a different file) and re-creation reopen the path.
+fn follow_file(path: PathBuf, start: StartAt, source: &str, sender: LineSender) {
+    let source = source.to_string();
+    thread::spawn(move || {
+        let mut start = start;
+        let mut line = vec![];
+        loop {
+            let Ok(mut file) = File::open(&path) else {
+                thread::sleep(FOLLOW_INTERVAL);
+                // Whatever shows up later is new
+                start = StartAt::Beginning;
+                continue;
+            };
+            let Ok(mut position) = seek_start(&mut file, start) else {
+                thread::sleep(FOLLOW_INTERVAL);
+                continue;
+            };
+            let identity = file.metadata().ok().and_then(|m| file_identity(&m));
+            let mut reader = BufReader::new(file);
+            // Rotated and re-created files are read from the beginning
+            start = StartAt::Beginning;
+            loop {
+                let read = line.len();
+                match reader.read_until(b'\n', &mut line) {
+                    Ok(0) => match fs::metadata(&path) {
+                        Ok(meta) if file_identity(&meta) != identity => {
+                            // The last line of the old file, without its line break,
+                            // stays apart from the first one of the new file
+                            if !line.is_empty() {
+                                line.push(b'\n');
+                                if !send_bytes(&sender, &source, &mut line) {
+                                    return;
+                                }
+                            }
+                            break;
+                        }
+                        Ok(meta) if meta.len() < position => {
+                            // Truncated
+                            if reader.seek(SeekFrom::Start(0)).is_err() {
+                                break;
+                            }
+                            position = 0;
+                            line.clear();
+                        }
+                        _ => thread::sleep(FOLLOW_INTERVAL),
+                    },
+                    Ok(len) => {
+                        position += len as u64;
+                        // Partial lines are completed on the next read
+                        if line.ends_with(b"\n") && !send_bytes(&sender, &source, &mut line) {
+                            return;
+                        }
+                    }
+                    Err(e) => {
+                        sender.send(InputLine::new(&source, e.to_string())).ok();
+                        thread::sleep(FOLLOW_INTERVAL);
+                        // Carry on from the last line read instead of the beginning
+                        line.truncate(read);
+                        if reader.seek(SeekFrom::Start(position)).is_err() {
+                            line.clear();
+                            break;
+                        }
+                    }
+                }
+            }
+        }
     });
 }
+
+/// Sends the bytes read as a line and clears them, `false` once nobody listens.
+///
+/// Invalid UTF-8 is replaced rather than failing the read.
+fn send_bytes(sender: &LineSender, source: &str, line: &mut Vec<u8>) -> bool {
+    let text = String::from_utf8_lossy(line).into_owned();
+    line.clear();
+    sender.send(InputLine::new(source, text)).is_ok()
+}
+
+/// Moves the file cursor to where the reading should start and returns its position.
+fn seek_start(file: &mut File, start: StartAt) -> io::Result<u64> {
+    match start {
+        StartAt::Beginning => file.seek(SeekFrom::Start(0)),
+        StartAt::End => file.seek(SeekFrom::End(0)),
+        StartAt::Lines(lines) => {
+            let offset = tail_offset(file, lines)?;
+            file.seek(SeekFrom::Start(offset))
+        }
+    }
+}
+
+/// Returns the offset where the last `lines` lines of the file start.
+fn tail_offset(file: &mut File, lines: usize) -> io::Result<u64> {
+    const CHUNK: u64 = 8192;
+    let len = file.seek(SeekFrom::End(0))?;
+    let mut end = len;
+    let mut found = 0;
+    let mut buf = vec![0; CHUNK as usize];
+    while end > 0 {
+        let start = end.saturating_sub(CHUNK);
+        let chunk = &mut buf[..(end - start) as usize];
+        file.seek(SeekFrom::Start(start))?;
+        file.read_exact(chunk)?;
+        for (i, byte) in chunk.iter().enumerate().rev() {
+            let offset = start + i as u64;
+            // The trailing new line belongs to the last line
+            if *byte == b'\n' && offset + 1 != len {
+                found += 1;
+                if found == lines {
+                    return Ok(offset + 1);
+                }
+            }
+        }
+        end = start;
+    }
+    Ok(0)
+}
+
+/// Identifies the file behind a path so that rotations can be detected.
+#[cfg(unix)]
+fn file_identity(meta: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((meta.dev(), meta.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_identity(_meta: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// Expands a glob pattern into the files it matches.
+///
+/// A pattern without matches is returned as is so that the file can be waited for.
+pub fn expand_follow(pattern: &str) -> Vec<String> {
+    let paths: Vec<String> = glob::glob(pattern)
+        .map(|paths| {
+            paths
+                .filter_map(|p| p.ok())
+                .filter(|p| p.is_file())
+                .map(|p| p.to_string_lossy().to_string())
+                .collect()
+        })
+        .unwrap_or_default();
+    if paths.is_empty() {
+        vec![pattern.to_string()]
+    } else {
+        paths
+    }
+}
+
+/// File name used to tag the lines of a followed file.
+pub fn follow_name(path: &str) -> &str {
+    Path::new(path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pending() {
+        let stdin = StdinHandler::new();
+        assert_eq!(stdin.pending(), 0);
+        stdin.sender.send("a".to_string()).unwrap();
+        stdin.sender.send("b".to_string()).unwrap();
+        assert_eq!(stdin.pending(), 2);
+        assert_eq!(stdin.try_recv().unwrap(), SourceEvent::from("a"));
+        assert_eq!(stdin.pending(), 1);
+        assert_eq!(stdin.recv().unwrap(), SourceEvent::from("b"));
+        assert_eq!(stdin.pending(), 0);
+        assert_eq!(stdin.try_recv(), Err(mpsc::TryRecvError::Empty));
+        assert_eq!(stdin.pending(), 0);
+    }
+
+    #[test]
+    fn record_and_replay() {
+        let path = std::env::temp_dir().join(format!("logss-replay-{}", std::process::id()));
+        let mut stdin = StdinHandler::new();
+        stdin.record(&path).unwrap();
+        stdin.sender.send("a\n").unwrap();
+        thread::sleep(Duration::from_millis(200));
+        let b = InputLine::new_stderr("api", "b\n".to_string());
+        stdin.sender.send(b.clone()).unwrap();
+
+        let replayed = StdinHandler::new();
+        let started = Instant::now();
+        replayed.replay(&path, Speed::Factor(2.0)).unwrap();
+        assert_eq!(replayed.recv().unwrap(), SourceEvent::from("a\n"));
+        assert_eq!(replayed.recv().unwrap(), SourceEvent::from(b));
+        let elapsed = started.elapsed();
+        assert!(elapsed >= Duration::from_millis(100), "{elapsed:?}");
+
+        let stepped = StdinHandler::new();
+        stepped.replay(&path, Speed::Step).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(stepped.try_recv(), Err(mpsc::TryRecvError::Empty));
+        stepped.step();
+        assert_eq!(stepped.recv().unwrap(), SourceEvent::from("a\n"));
+        fs::remove_file(&path).unwrap();
+        assert!(stepped.replay(&path, Speed::Step).is_err());
+    }
+
+    #[test]
+    fn init_sources() {
+        let stdin = StdinHandler::new();
+        let sources = vec![
+            LocalSource {
+                name: Some("one".to_string()),
+                command: vec!["echo".to_string(), "1".to_string()],
+                ..Default::default()
+            },
+            LocalSource {
+                name: Some("two".to_string()),
+                command: vec!["echo".to_string(), "2".to_string()],
+                ..Default::default()
+            },
+        ];
+        stdin.init(&sources).unwrap();
+        let mut lines = vec![];
+        while lines.len() < 2 {
+            if let SourceEvent::Line(line) = stdin.recv().unwrap() {
+                lines.push(line);
+            }
+        }
+        lines.sort_by(|a, b| a.source.cmp(&b.source));
+        assert_eq!(lines[0], InputLine::new("one", "1\n".to_string()));
+        assert_eq!(lines[1], InputLine::new("two", "2\n".to_string()));
+    }
+
+    #[test]
+    fn init_empty_command() {
+        let stdin = StdinHandler::new();
+        let sources = vec![LocalSource {
+            name: Some("empty".to_string()),
+            command: vec![],
+            ..Default::default()
+        }];
+        assert!(stdin.init(&sources).is_err());
+    }
+
+    #[test]
+    fn stderr_and_exit_status() {
+        let stdin = StdinHandler::new();
+        let sources = vec![LocalSource {
+            name: Some("sh".to_string()),
+            command: ["sh", "-c", "echo out; echo err >&2; exit 3"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            ..Default::default()
+        }];
+        stdin.init(&sources).unwrap();
+        let mut events = vec![];
+        loop {
+            let event = stdin.recv().unwrap();
+            if let SourceEvent::Exited { .. } = event {
+                events.push(event);
+                break;
+            }
+            events.push(event);
+        }
+        assert_eq!(events.len(), 3);
+        assert!(events.contains(&InputLine::new("sh", "out\n".to_string()).into()));
+        assert!(events.contains(&InputLine::new_stderr("sh", "err\n".to_string()).into()));
+        assert_eq!(
+            events[2],
+            SourceEvent::Exited {
+                source: "sh".to_string(),
+                status: Some(3),
+                restart_in: None,
+            }
+        );
+    }
+
+    #[test]
+    fn restart() {
+        let stdin = StdinHandler::new();
+        let sources = vec![LocalSource {
+            name: Some("sh".to_string()),
+            command: ["sh", "-c", "echo out; exit 1"]
+                .iter()
+                .map(|s| s.to_string())
+                .collect(),
+            restart: Some(Restart::OnFailure),
+            ..Default::default()
+        }];
+        stdin.init(&sources).unwrap();
+        let mut events = vec![];
+        while events.len() < 4 {
+            events.push(stdin.recv().unwrap());
+        }
+        let out = SourceEvent::from(InputLine::new("sh", "out\n".to_string()));
+        assert_eq!(events[0], out);
+        assert_eq!(
+            events[1],
+            SourceEvent::Exited {
+                source: "sh".to_string(),
+                status: Some(1),
+                restart_in: Some(RESTART_BACKOFF_MIN),
+            }
+        );
+        assert_eq!(
+            events[2],
+            SourceEvent::Restarted {
+                source: "sh".to_string(),
+                attempt: 1,
+            }
+        );
+        assert_eq!(events[3], out);
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn restart_failure() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let _ = fs::remove_dir_all("test-restart");
+        fs::create_dir_all("test-restart").unwrap();
+        // The command is gone once it has run
+        let path = "test-restart/run.sh";
+        fs::write(path, "#!/bin/sh\nrm \"$0\"\nexit 1\n").unwrap();
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755)).unwrap();
+        let stdin = StdinHandler::new();
+        let sources = vec![LocalSource {
+            name: Some("run".to_string()),
+            command: vec![path.to_string()],
+            restart: Some(Restart::OnFailure),
+            ..Default::default()
+        }];
+        stdin.init(&sources).unwrap();
+        assert!(matches!(
+            stdin.recv().unwrap(),
+            SourceEvent::Exited {
+                status: Some(1),
+                ..
+            }
+        ));
+        let SourceEvent::Failed {
+            source,
+            error,
+            restart_in,
+        } = stdin.recv().unwrap()
+        else {
+            panic!("the command should have failed to start");
+        };
+        assert_eq!(source, "run");
+        assert!(error.contains("No such file"), "{error}");
+        assert_eq!(restart_in, Some(RESTART_BACKOFF_MIN * 2));
+        let _ = fs::remove_dir_all("test-restart");
+    }
+
+    #[test]
+    fn no_restart_on_success() {
+        let stdin = StdinHandler::new();
+        let sources = vec![LocalSource {
+            name: Some("true".to_string()),
+            command: vec!["true".to_string()],
+            restart: Some(Restart::OnFailure),
+            ..Default::default()
+        }];
+        stdin.init(&sources).unwrap();
+        assert_eq!(
+            stdin.recv().unwrap(),
+            SourceEvent::Exited {
+                source: "true".to_string(),
+                status: Some(0),
+                restart_in: None,
+            }
+        );
+    }
+
+    fn recv_text(stdin: &StdinHandler) -> Option<String> {
+        for _ in 0..50 {
+            if let Ok(SourceEvent::Line(line)) = stdin.try_recv() {
+                return Some(line.text);
+            }
+            thread::sleep(Duration::from_millis(50));
+        }
+        None
+    }
+
+    #[test]
+    fn tail_offset() {
+        let _ = fs::remove_dir_all("test-tail");
+        fs::create_dir_all("test-tail").unwrap();
+        fs::write("test-tail/a.log", "1\n2\n3\n").unwrap();
+        let mut file = File::open("test-tail/a.log").unwrap();
+        assert_eq!(super::tail_offset(&mut file, 1).unwrap(), 4);
+        assert_eq!(super::tail_offset(&mut file, 2).unwrap(), 2);
+        assert_eq!(super::tail_offset(&mut file, 3).unwrap(), 0);
+        assert_eq!(super::tail_offset(&mut file, 10).unwrap(), 0);
+        let _ = fs::remove_dir_all("test-tail");
+    }
+
+    #[test]
+    fn expand_follow() {
+        let _ = fs::remove_dir_all("test-glob");
+        fs::create_dir_all("test-glob").unwrap();
+        fs::write("test-glob/a.log", "").unwrap();
+        fs::write("test-glob/b.log", "").unwrap();
+        fs::write("test-glob/c.txt", "").unwrap();
+        assert_eq!(
+            super::expand_follow("test-glob/*.log"),
+            vec!["test-glob/a.log", "test-glob/b.log"]
+        );
+        assert_eq!(
+            super::expand_follow("test-glob/d.log"),
+            vec!["test-glob/d.log"]
+        );
+        assert_eq!(follow_name("test-glob/d.log"), "d.log");
+        let _ = fs::remove_dir_all("test-glob");
+    }
+
+    #[test]
+    fn follow() {
+        let _ = fs::remove_dir_all("test-follow");
+        fs::create_dir_all("test-follow").unwrap();
+        let path = "test-follow/a.log";
+        fs::write(path, "old 1\nold 2\n").unwrap();
+        let stdin = StdinHandler::new();
+        let sources = vec![LocalSource {
+            name: Some("a".to_string()),
+            follow: Some(path.to_string()),
+            lines: Some(1),
+            ..Default::default()
+        }];
+        stdin.init(&sources).unwrap();
+        assert_eq!(recv_text(&stdin), Some("old 2\n".to_string()));
+
+        // Appended
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        std::io::Write::write_all(&mut file, b"new 1\n").unwrap();
+        assert_eq!(recv_text(&stdin), Some("new 1\n".to_string()));
+
+        // Truncated
+        thread::sleep(FOLLOW_INTERVAL);
+        fs::write(path, "t\n").unwrap();
+        assert_eq!(recv_text(&stdin), Some("t\n".to_string()));
+
+        // Rotated and re-created
+        fs::rename(path, "test-follow/a.log.1").unwrap();
+        fs::write(path, "rotated\n").unwrap();
+        assert_eq!(recv_text(&stdin), Some("rotated\n".to_string()));
+        let _ = fs::remove_dir_all("test-follow");
+    }
+
+    #[test]
+    fn follow_no_lines() {
+        let _ = fs::remove_dir_all("test-follow-zero");
+        fs::create_dir_all("test-follow-zero").unwrap();
+        let path = "test-follow-zero/a.log";
+        fs::write(path, "old 1\nold 2\n").unwrap();
+        let stdin = StdinHandler::new();
+        let sources = vec![LocalSource {
+            name: Some("a".to_string()),
+            follow: Some(path.to_string()),
+            lines: Some(0),
+            ..Default::default()
+        }];
+        stdin.init(&sources).unwrap();
+        thread::sleep(FOLLOW_INTERVAL);
+        assert_eq!(stdin.try_recv(), Err(mpsc::TryRecvError::Empty));
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        std::io::Write::write_all(&mut file, b"new\n").unwrap();
+        assert_eq!(recv_text(&stdin), Some("new\n".to_string()));
+        let _ = fs::remove_dir_all("test-follow-zero");
+    }
+
+    #[test]
+    fn follow_invalid_utf8() {
+        let _ = fs::remove_dir_all("test-follow-utf8");
+        fs::create_dir_all("test-follow-utf8").unwrap();
+        let path = "test-follow-utf8/a.log";
+        fs::write(path, b"bad \xff line\n").unwrap();
+        let stdin = StdinHandler::new();
+        let sources = vec![LocalSource {
+            name: Some("a".to_string()),
+            follow: Some(path.to_string()),
+            from_start: Some(true),
+            ..Default::default()
+        }];
+        stdin.init(&sources).unwrap();
+        assert_eq!(recv_text(&stdin), Some("bad \u{fffd} line\n".to_string()));
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        std::io::Write::write_all(&mut file, b"next\n").unwrap();
+        assert_eq!(recv_text(&stdin), Some("next\n".to_string()));
+        // Nothing is read twice
+        thread::sleep(FOLLOW_INTERVAL * 3);
+        assert_eq!(stdin.try_recv(), Err(mpsc::TryRecvError::Empty));
+        let _ = fs::remove_dir_all("test-follow-utf8");
+    }
+
+    #[test]
+    fn follow_rotated_partial_line() {
+        let _ = fs::remove_dir_all("test-follow-partial");
+        fs::create_dir_all("test-follow-partial").unwrap();
+        let path = "test-follow-partial/a.log";
+        fs::write(path, "").unwrap();
+        let stdin = StdinHandler::new();
+        let sources = vec![LocalSource {
+            name: Some("a".to_string()),
+            follow: Some(path.to_string()),
+            ..Default::default()
+        }];
+        stdin.init(&sources).unwrap();
+        thread::sleep(FOLLOW_INTERVAL);
+        let mut file = fs::OpenOptions::new().append(true).open(path).unwrap();
+        std::io::Write::write_all(&mut file, b"full\npartial").unwrap();
+        assert_eq!(recv_text(&stdin), Some("full\n".to_string()));
+
+        fs::rename(path, "test-follow-partial/a.log.1").unwrap();
+        fs::write(path, "new\n").unwrap();
+        assert_eq!(recv_text(&stdin), Some("partial\n".to_string()));
+        assert_eq!(recv_text(&stdin), Some("new\n".to_string()));
+        let _ = fs::remove_dir_all("test-follow-partial");
+    }
+}
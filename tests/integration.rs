@@ -13,6 +13,17 @@ fn non_valid_arg() {
         .stderr(predicate::str::contains("Error: non valid arguments"));
 }
 
+#[test]
+fn empty_command() {
+    let mut cmd = Command::cargo_bin("logss").unwrap();
+    cmd.arg("-C").arg("  ");
+
+    cmd.assert()
+        .failure()
+        .stdout(predicate::str::is_empty())
+        .stderr(predicate::str::contains("empty command"));
+}
+
 #[test]
 fn show_help() {
     let mut cmd = Command::cargo_bin("logss").unwrap();
@@ -27,13 +38,32 @@ Usage: logss [OPTIONS]
 
 Options:
   -c <CONTAINERS>  Specify substrings (regex patterns)
+  -E <EXPR>        Container matching an expression, e.g. 'ERROR and not timeout'
+  -x <EXCLUDE>     Hide lines matching this pattern everywhere (repeatable)
   -e               Exit on empty input [default: false]
   -s               Start in single view mode [default: false]
-  -C <COMMAND>     Get input from a command
+  -C <COMMAND>     Get input from a command (repeatable, '-' reads stdin)
+  -R <RESTART>     Restart commands that exit: always, on-failure or never [default: never]
+  -p <FORMAT>      Parse input lines as: json, logfmt or raw [default: raw]
+  -F <PATH>        Follow a file, glob patterns allowed (repeatable)
+  -n <LINES>       Read the last LINES of followed files, 'all' for everything [default: 0]
+  -M <REGEX>       Lines matching it continue the event before them, e.g. '^\\s'
   -f <FILE>        Input configuration file (overrides CLI arguments)
   -o <OUTPUT_PATH> Specify the output path for matched patterns
+  -T <TEMPLATE>    Output file names, with {date}, {container} and {id} [default: {container}.txt]
+  -Z <SIZE>        Rotate the output files at SIZE, e.g. 10M
+  -I <INTERVAL>    Rotate the output files every INTERVAL, e.g. 1h
+  -z               Compress the rotated output files with gzip
+  -w <FILE>        Record every input line, with its timing, to FILE
+  -P <FILE>        Replay a recording instead of reading the inputs
+  -S <SPEED>       Replay speed: a factor like 2 or 10, or 'step' [default: 1]
+  -L <FILE>        Where the session is saved with 'S' [default: logss-session.yaml]
+  -b               Save the lines of the buffers with the session
+  -X <FORMAT>      Format of the buffers exported with 'e': text, jsonl or ansi [default: text]
   -r <RENDER>      Define render speed in milliseconds [default: 100]
   -t <THREADS>     Number of threads per container for triggers [default: 1]
+  -Q <SIZE>        Number of triggers that can wait per container [default: 100]
+  -O <OVERFLOW>    When the trigger queue is full: drop, coalesce or block [default: drop]
   -V               Start in vertical view mode
   -h               Print help
 ",